@@ -1,9 +1,12 @@
+//! What rays see when they leave the scene.
+
 use crate::color::Color;
 use crate::ray::Ray;
 
 pub mod background_color;
 pub mod hdri;
 
+/// The color of rays that don't hit any object.
 pub trait Background: Send + Sync {
     fn background(&self, ray: &Ray) -> Color;
 }
@@ -4,7 +4,6 @@ use crate::color::Color;
 #[derive(Debug, Copy, Clone, Default)]
 pub struct BackgroundColor(pub Color);
 
-#[allow(dead_code)]
 impl BackgroundColor {
     pub const fn new(r: f64, g: f64, b: f64) -> Self {
         Self(Color::new(r, g, b))
@@ -2,8 +2,9 @@ use std::f64::consts::PI;
 use std::path::Path;
 
 use crate::background::Background;
+use crate::color::space::{ColorSpace, WorkingSpace};
 use crate::color::Color;
-use crate::image::Image;
+use crate::image::{Image, ImageError};
 use crate::ray::Ray;
 use crate::vec::Vec3;
 
@@ -14,12 +15,27 @@ pub struct Hdri {
 }
 
 impl Hdri {
-    pub fn load<P: AsRef<Path>>(path: P, strength: f64, rotation: f64) -> Self {
-        Self {
-            texture: Image::load(path),
+    /// Load an environment map in equirectangular projection, converting it into
+    /// `working_space`.
+    ///
+    /// 8-bit images are decoded from sRGB, floating point ones are taken as linear sRGB.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image can't be loaded.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        strength: f64,
+        rotation: f64,
+        working_space: WorkingSpace,
+    ) -> Result<Self, ImageError> {
+        let color_space = ColorSpace::for_file(&path);
+
+        Ok(Self {
+            texture: Image::load(path)?.map(|color| color_space.decode(color, working_space)),
             strength,
             rotation: rotation.to_radians(),
-        }
+        })
     }
 
     fn uv(&self, point: &Vec3) -> (f64, f64) {
@@ -1,33 +1,23 @@
-use std::io;
-use std::sync::mpsc::Sender;
+//! The camera and its render settings.
+
 use std::sync::Arc;
 
 use crate::background::background_color::BackgroundColor;
 use crate::background::Background;
+use crate::color::space::WorkingSpace;
 use crate::color::Color;
+use crate::hit::lights::Lights;
 use crate::hit::Hit;
-use crate::image::{Image, ImageError};
-use crate::math::interval::Interval;
+use crate::image::Image;
+use crate::integrator::path::PathTracer;
+use crate::integrator::{Integrator, PathEvent};
 use crate::ray::Ray;
+use crate::sampler::sobol::Sobol;
+use crate::sampler::{SampleId, SampleStream, Sampler};
 use crate::vec::Vec3;
 use crate::viewport::Viewport;
 
-const BIAS: f64 = 0.001;
-
-#[derive(Debug)]
-pub enum CameraError {
-    #[allow(dead_code)] // can be removed as soon as this error is logged correctly
-    IOError(io::Error),
-    #[allow(dead_code)] // can be removed as soon as this error is logged correctly
-    Averaging(ImageError),
-}
-
-impl From<io::Error> for CameraError {
-    fn from(error: io::Error) -> Self {
-        Self::IOError(error)
-    }
-}
-
+/// Builds a [`Camera`] for a specific render target.
 #[derive(Clone)]
 pub struct CameraBuilder {
     position: Vec3,
@@ -38,7 +28,13 @@ pub struct CameraBuilder {
     fov: f64,
     samples: u32,
     max_bounces: u32,
+    roulette_depth: u32,
     background: Arc<dyn Background>,
+    lights: Arc<Lights>,
+    integrator: Arc<dyn Integrator>,
+    sampler: Arc<dyn Sampler>,
+    seed: u64,
+    working_space: WorkingSpace,
 }
 
 impl Default for CameraBuilder {
@@ -52,12 +48,17 @@ impl Default for CameraBuilder {
             fov: 80.0,
             samples: 9,
             max_bounces: 50,
+            roulette_depth: 3,
             background: Arc::new(BackgroundColor::default()),
+            lights: Arc::new(Lights::default()),
+            integrator: Arc::new(PathTracer),
+            sampler: Arc::new(Sobol),
+            seed: 0,
+            working_space: WorkingSpace::LinearSrgb,
         }
     }
 }
 
-#[allow(dead_code)]
 impl CameraBuilder {
     pub fn new(focus_distance: f64, defocus_angle: f64, fov: f64) -> Self {
         Self {
@@ -93,11 +94,59 @@ impl CameraBuilder {
         self
     }
 
+    /// Randomly end paths that carry little light once they bounced this many times.
+    ///
+    /// The surviving paths are weighted up to make up for the ones that were ended, so this
+    /// doesn't darken the image. `max_bounces` still limits the length of every path.
+    pub const fn with_roulette_depth(mut self, roulette_depth: u32) -> Self {
+        self.roulette_depth = roulette_depth;
+        self
+    }
+
     pub fn with_background(mut self, background: impl Background + 'static) -> Self {
         self.background = Arc::new(background);
         self
     }
 
+    /// Sample these lights directly at every surface that doesn't scatter into discrete
+    /// directions, instead of only finding them by chance.
+    pub fn with_lights(mut self, lights: Lights) -> Self {
+        self.lights = Arc::new(lights);
+        self
+    }
+
+    /// Estimate the light arriving along the camera rays with this integrator instead of a
+    /// [`PathTracer`].
+    pub fn with_integrator(mut self, integrator: impl Integrator + 'static) -> Self {
+        self.integrator = Arc::new(integrator);
+        self
+    }
+
+    /// Choose the numbers for the random decisions of every sample with this sampler instead of
+    /// [`Sobol`].
+    pub fn with_sampler(mut self, sampler: impl Sampler + 'static) -> Self {
+        self.sampler = Arc::new(sampler);
+        self
+    }
+
+    /// Choose the random numbers of the render with this seed.
+    ///
+    /// Every sample of every pixel derives its numbers from the seed, its pixel and its index
+    /// alone, so renders with the same seed and settings are identical, no matter how many
+    /// threads render them.
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Render in `working_space`, which the colors of the scene have to be given in.
+    ///
+    /// The rendered image is always converted back to linear sRGB.
+    pub const fn with_working_space(mut self, working_space: WorkingSpace) -> Self {
+        self.working_space = working_space;
+        self
+    }
+
     pub fn look_at(mut self, look_at: Vec3) -> Self {
         self.forward = self.position.look_at(&look_at);
         self
@@ -127,11 +176,18 @@ impl CameraBuilder {
             defocus_disk: (right * defocus_radius, up * defocus_radius),
             samples: self.samples,
             max_bounces: self.max_bounces,
+            roulette_depth: self.roulette_depth,
             background: self.background,
+            lights: self.lights,
+            integrator: self.integrator,
+            sampler: self.sampler,
+            seed: self.seed,
+            working_space: self.working_space,
         }
     }
 }
 
+/// Generates the rays for every pixel of the render target and traces them through the scene.
 #[derive(Clone)]
 pub struct Camera {
     pub position: Vec3,
@@ -139,94 +195,96 @@ pub struct Camera {
     defocus_disk: (Vec3, Vec3),
     pub samples: u32,
     pub max_bounces: u32,
+    pub roulette_depth: u32,
     pub background: Arc<dyn Background>,
+    pub lights: Arc<Lights>,
+    pub integrator: Arc<dyn Integrator>,
+    pub sampler: Arc<dyn Sampler>,
+    pub seed: u64,
+    pub working_space: WorkingSpace,
 }
 
 impl Camera {
-    pub fn render(
-        &self,
-        root: &Arc<dyn Hit>,
-        samples: f64,
-        mut target: Image,
-        samples_tx: &Sender<((u32, u32), Color)>,
-        log: bool,
-    ) -> Image {
-        let samples_sqrt = samples.sqrt();
-        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-        let dimension_indices = 0..samples_sqrt as u32;
-        let subpixel_scale = 1.0 / samples_sqrt;
-
-        for y in 0..target.height() {
-            for x in 0..target.width() {
-                let mut color = Color::black();
-
-                for sample_y in dimension_indices.clone() {
-                    for sample_x in dimension_indices.clone() {
-                        let sample =
-                            self.viewport
-                                .pixel_sample(x, y, sample_x, sample_y, subpixel_scale);
-                        let defocus_sample = Vec3::random_in_unit_disk();
-                        let ray = Ray::look_at(
-                            self.position
-                                + defocus_sample.0 * self.defocus_disk.0
-                                + defocus_sample.1 * self.defocus_disk.1,
-                            sample,
-                            rand::random(),
-                        );
-
-                        color += self.ray_color(root.clone(), ray, 0);
-                    }
-                }
-                color /= samples;
-                color.clamp();
-
-                let _ = samples_tx.send(((x, y), color));
-                target.set_pixel(x, y, color);
-
-                #[allow(clippy::cast_precision_loss)]
-                if log {
-                    print!(
-                        "\rprogress: {:.2}%",
-                        f64::from(y * target.width() + x) * 100.0 / target.pixel_count() as f64
-                    );
-                }
-            }
-        }
-        if log {
-            println!();
-        }
-
-        target
+    /// Trace all samples of the pixel at `x`, `y` and return their average.
+    pub fn render_pixel(&self, root: &dyn Hit, x: u32, y: u32) -> Color {
+        let samples = self.samples.max(1);
+        let sum = (0..samples).fold(Color::black(), |sum, sample| {
+            sum + self.sample_pixel(root, x, y, sample)
+        });
+
+        sum / f64::from(samples)
     }
 
-    fn ray_color(&self, root: Arc<dyn Hit>, ray: Ray, bounces: u32) -> Color {
-        if bounces >= self.max_bounces {
-            return Color::black();
-        }
+    /// Trace a single sample of the pixel at `x`, `y`.
+    ///
+    /// `sample` chooses the numbers the [`Sampler`] provides for the sample. Any run of
+    /// consecutive samples as long as the camera's number of samples covers the pixel evenly.
+    pub fn sample_pixel(&self, root: &dyn Hit, x: u32, y: u32, sample: u32) -> Color {
+        self.trace_sample(root, x, y, sample, &mut |_| {})
+    }
 
-        if let Some(hit) = root.hit(&ray, Interval(BIAS..f64::INFINITY)) {
-            let emitted = hit.material.emitted(hit.u, hit.v, &hit.point);
+    /// Trace a single sample of the pixel at `x`, `y` like [`Camera::sample_pixel`], reporting
+    /// every step of its path to `record`.
+    pub fn trace_sample(
+        &self,
+        root: &dyn Hit,
+        x: u32,
+        y: u32,
+        sample: u32,
+        record: &mut dyn FnMut(PathEvent),
+    ) -> Color {
+        let mut samples = SampleStream::new(
+            self.sampler.as_ref(),
+            SampleId {
+                seed: self.seed,
+                pixel: (x, y),
+                index: sample,
+                count: self.samples.max(1),
+            },
+        );
+        let target = self.viewport.pixel_sample(x, y, samples.pixel());
+        let lens = Vec3::in_unit_disk(samples.lens());
+        let ray = Ray::look_at(
+            self.position + lens.0 * self.defocus_disk.0 + lens.1 * self.defocus_disk.1,
+            target,
+            samples.time(),
+        );
 
-            if let Some((scattered, attenuation)) = hit.material.scatter(&ray, &hit) {
-                emitted + attenuation * self.ray_color(root, scattered, bounces + 1)
-            } else {
-                emitted
-            }
-        } else {
-            self.background.background(&ray)
-        }
+        self.integrator
+            .radiance(self, root, ray, &mut samples, record)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use approx::assert_abs_diff_eq;
 
+    use crate::background::background_color::BackgroundColor;
+    use crate::background::Background;
     use crate::camera::CameraBuilder;
     use crate::color::Color;
+    use crate::hit::bvh::flat::FlatBvh;
     use crate::image::Image;
+    use crate::ray::Ray;
+    use crate::sampler::stratified::{cell, Stratified};
     use crate::vec::Vec3;
 
+    /// A black background that keeps the directions of the rays reaching it.
+    struct RecordDirections(Arc<Mutex<Vec<Vec3>>>);
+
+    impl Background for RecordDirections {
+        fn background(&self, ray: &Ray) -> Color {
+            self.0
+                .lock()
+                .expect("could not lock directions")
+                .push(ray.direction);
+
+            Color::black()
+        }
+    }
+
     #[test]
     fn created_correctly() {
         let camera = CameraBuilder::new(1.0, 10.0, 90.0)
@@ -238,4 +296,55 @@ mod tests {
         assert_abs_diff_eq!(camera.viewport.edges.0.normalized(), Vec3(1.0, 0.0, 0.0));
         assert_abs_diff_eq!(camera.viewport.edges.1.normalized(), Vec3(0.0, -1.0, 0.0));
     }
+
+    #[test]
+    fn non_square_sample_count() {
+        let camera = CameraBuilder::default()
+            .with_samples(10)
+            .with_background(BackgroundColor::new(0.2, 0.4, 0.6))
+            .build(&Image::with_dimensions(4, 4, Color::black()));
+
+        assert_abs_diff_eq!(
+            camera.render_pixel(&FlatBvh::new(Vec::new()), 1, 2),
+            Color::new(0.2, 0.4, 0.6),
+            epsilon = 1e-12
+        );
+
+        // every sample lands in its own cell of the pixel
+        let directions = Arc::new(Mutex::new(Vec::new()));
+        let camera = CameraBuilder::default()
+            .with_forward(Vec3(0.0, 0.0, -1.0))
+            .with_samples(10)
+            .with_sampler(Stratified)
+            .with_background(RecordDirections(directions.clone()))
+            .build(&Image::with_dimensions(4, 4, Color::black()));
+        camera.render_pixel(&FlatBvh::new(Vec::new()), 1, 2);
+
+        let (right, down) = camera.viewport.pixel_size();
+        let offsets = directions
+            .lock()
+            .expect("could not lock directions")
+            .iter()
+            .map(|direction| {
+                // the viewport is one unit in front of the camera at the origin
+                let on_viewport = *direction / -direction.2 - camera.viewport.origin;
+                (
+                    on_viewport.dot(&right) / right.dot(&right) - 1.0,
+                    on_viewport.dot(&down) / down.dot(&down) - 2.0,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(offsets.len(), 10);
+        for index in 0..10 {
+            let ((x_min, x_max), (y_min, y_max)) = cell(10, index);
+            let inside = offsets
+                .iter()
+                .filter(|(x, y)| {
+                    (x_min - 1e-9..x_max + 1e-9).contains(x)
+                        && (y_min - 1e-9..y_max + 1e-9).contains(y)
+                })
+                .count();
+            assert_eq!(inside, 1, "cell {index} of {offsets:?}");
+        }
+    }
 }
@@ -0,0 +1,601 @@
+use std::ffi::OsStr;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+
+use raytracer::camera::CameraBuilder;
+use raytracer::color::Color;
+use raytracer::image::{ExrPrecision, Image, ImageError};
+use raytracer::integrator::brute_force::BruteForce;
+use raytracer::integrator::path::PathTracer;
+use raytracer::render::aov::Aov;
+use raytracer::render::{RenderOutput, StoppingCriterion};
+use raytracer::sampler::halton::Halton;
+use raytracer::sampler::independent::Independent;
+use raytracer::sampler::sobol::Sobol;
+use raytracer::sampler::stratified::Stratified;
+use raytracer::scene_file::SceneFile;
+use raytracer::tone_map::{ToneMapOperator, ToneMapper};
+
+/// Render a scene with the raytracer.
+///
+/// Values given on the command line override the ones from the scene file.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Options {
+    /// Name of a scene in the `scenes` directory.
+    #[arg(short, long, default_value = "mis", conflicts_with = "scene_file")]
+    pub scene: String,
+    /// Path to a scene file to render instead of one of the included scenes.
+    #[arg(short = 'f', long)]
+    pub scene_file: Option<PathBuf>,
+    /// Width of the rendered image in pixels.
+    #[arg(short, long, default_value_t = 512)]
+    pub width: u32,
+    /// Height of the rendered image in pixels. Takes precedence over the aspect ratio.
+    #[arg(long)]
+    pub height: Option<u32>,
+    /// Aspect ratio (width / height) of the rendered image.
+    #[arg(short, long, default_value_t = 1.0, value_parser = parse_positive)]
+    pub aspect_ratio: f64,
+    /// Number of samples per pixel.
+    #[arg(long)]
+    pub samples: Option<NonZeroU32>,
+    /// Maximum number of bounces per path.
+    #[arg(short, long)]
+    pub max_bounces: Option<u32>,
+    /// Number of bounces after which paths carrying little light may be ended early.
+    #[arg(long)]
+    pub roulette_depth: Option<u32>,
+    /// How to estimate the light arriving at the camera.
+    #[arg(long, value_enum, default_value_t = IntegratorKind::Path)]
+    pub integrator: IntegratorKind,
+    /// How to choose the numbers for the random decisions of every sample.
+    #[arg(long, value_enum, default_value_t = SamplerKind::Sobol)]
+    pub sampler: SamplerKind,
+    /// Seed for the random numbers of the render and the noise textures. Renders with the same
+    /// seed and settings are identical.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Render the whole image in passes of one sample per pixel instead of tile by tile.
+    #[arg(long)]
+    pub progressive: bool,
+    /// Sample adaptively: give every pixel this many samples first, then spend the rest where
+    /// the image is noisiest. The number of samples becomes the average per pixel.
+    #[arg(long, value_name = "BASE_SAMPLES", num_args = 0..=1, default_missing_value = "16")]
+    pub adaptive: Option<u32>,
+    /// Also write a gray scale PNG of how many samples every pixel received.
+    #[arg(long, value_name = "PATH")]
+    pub sample_map: Option<PathBuf>,
+    /// Render in passes until this many seconds have passed, instead of a fixed number of
+    /// samples.
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        value_parser = parse_seconds,
+        conflicts_with = "target_noise"
+    )]
+    pub time_budget: Option<Duration>,
+    /// Render in passes until the estimated noise drops below this fraction of the pixel
+    /// brightness (e.g. 0.01 for 1 %), instead of a fixed number of samples.
+    #[arg(long, value_name = "FRACTION", value_parser = parse_positive)]
+    pub target_noise: Option<f64>,
+    /// Number of render threads. Defaults to the available parallelism.
+    #[arg(short, long)]
+    pub threads: Option<NonZeroU32>,
+    /// Where to write the rendered image.
+    #[arg(short, long, default_value = "output/result.png")]
+    pub output: PathBuf,
+    /// Format of the written image. Guessed from the output path if not given.
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+    /// How to map colors brighter than white into the displayable range, for PNG and PPM images
+    /// and the preview.
+    #[arg(long, value_enum, default_value_t = ToneMapKind::Clamp)]
+    pub tone_map: ToneMapKind,
+    /// Exposure in stops applied before tone mapping, e.g. 1 to double the brightness.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    pub exposure: f64,
+    /// The brightness that becomes white with the extended Reinhard operator.
+    #[arg(long, default_value_t = 4.0, value_parser = parse_positive)]
+    pub white_point: f64,
+    /// Store the channels of EXR images as 32-bit floats instead of 16-bit halves.
+    #[arg(long)]
+    pub exr_float: bool,
+    /// Also render these AOVs, separated by commas. They become layers of EXR images, and
+    /// separate files next to the output otherwise, e.g. `result.depth.png`.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub aov: Vec<AovKind>,
+    /// Render without opening the preview window. Always the case if the `preview` feature is
+    /// disabled.
+    #[arg(long, alias = "headless")]
+    pub no_preview: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum IntegratorKind {
+    /// Sample the lights directly and combine them with the scattered rays.
+    Path,
+    /// Only follow the scattered rays. Slow to converge, but useful as a reference.
+    BruteForce,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SamplerKind {
+    /// Independent random numbers.
+    Independent,
+    /// One sample in every stratum of every dimension.
+    Stratified,
+    /// The Halton sequence, shifted randomly per pixel.
+    Halton,
+    /// The Sobol sequence with Owen scrambling.
+    Sobol,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ToneMapKind {
+    /// Cut off every channel at white.
+    Clamp,
+    /// Compress all brightnesses, never quite reaching white.
+    Reinhard,
+    /// Compress all brightnesses, reaching white at the white point.
+    ExtendedReinhard,
+    /// The filmic curve of ACES.
+    Aces,
+    /// Filmic, desaturating bright colors towards white.
+    Agx,
+    /// The filmic curve from Uncharted 2.
+    Hable,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum AovKind {
+    /// The distance from the camera.
+    Depth,
+    /// The world space normal.
+    Normal,
+    /// The color of the surface.
+    Albedo,
+    /// The texture coordinates.
+    Uv,
+    /// The object, numbered in the order of the scene file.
+    ObjectId,
+    /// The material, numbered in the order of the scene file.
+    MaterialId,
+    /// Light reaching the first surface straight from a light.
+    Direct,
+    /// Light reaching the first surface after bouncing.
+    Indirect,
+    /// Light emitted by the first surface or the background.
+    Emission,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Png,
+    Ppm,
+    /// EXR with the linear colors, including everything brighter than white.
+    Exr,
+    /// Radiance HDR with the linear colors, including everything brighter than white.
+    Hdr,
+    /// Portable float map with the linear colors, including everything brighter than white.
+    Pfm,
+}
+
+impl Options {
+    /// The scene file to render.
+    pub fn scene_path(&self) -> PathBuf {
+        self.scene_file
+            .clone()
+            .unwrap_or_else(|| Path::new("scenes").join(&self.scene).with_extension("ron"))
+    }
+
+    /// An empty image with the requested resolution.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image would have more pixels than its buffer can hold.
+    pub fn target(&self) -> Result<Image, clap::Error> {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let height = self
+            .height
+            .unwrap_or_else(|| (f64::from(self.width) / self.aspect_ratio) as u32);
+        if self.width.max(1).checked_mul(height.max(1)).is_none() {
+            return Err(Self::command().error(
+                ErrorKind::ValueValidation,
+                format!(
+                    "a resolution of {}x{height} has too many pixels",
+                    self.width
+                ),
+            ));
+        }
+
+        Ok(Image::with_dimensions(self.width, height, Color::black()))
+    }
+
+    pub fn num_threads(&self) -> u32 {
+        self.threads.map_or_else(
+            || {
+                thread::available_parallelism().map_or(1, |threads| {
+                    u32::try_from(threads.get()).unwrap_or(u32::MAX)
+                })
+            },
+            NonZeroU32::get,
+        )
+    }
+
+    /// Apply the scene settings from the command line on top of the ones from the scene file.
+    pub const fn override_scene(&self, scene: SceneFile) -> SceneFile {
+        match self.seed {
+            Some(seed) => scene.with_seed(seed),
+            None => scene,
+        }
+    }
+
+    /// Apply the camera settings from the command line on top of the ones from the scene.
+    pub fn override_camera(&self, mut camera: CameraBuilder) -> CameraBuilder {
+        if let Some(samples) = self.samples {
+            camera = camera.with_samples(samples.get());
+        }
+        if let Some(max_bounces) = self.max_bounces {
+            camera = camera.with_max_bounces(max_bounces);
+        }
+        if let Some(roulette_depth) = self.roulette_depth {
+            camera = camera.with_roulette_depth(roulette_depth);
+        }
+        camera = match self.integrator {
+            IntegratorKind::Path => camera.with_integrator(PathTracer),
+            IntegratorKind::BruteForce => camera.with_integrator(BruteForce),
+        };
+        camera = match self.sampler {
+            SamplerKind::Independent => camera.with_sampler(Independent),
+            SamplerKind::Stratified => camera.with_sampler(Stratified),
+            SamplerKind::Halton => camera.with_sampler(Halton),
+            SamplerKind::Sobol => camera.with_sampler(Sobol),
+        };
+
+        camera
+    }
+
+    /// When the render is finished, depending on which of the options was given.
+    pub const fn stopping_criterion(&self) -> StoppingCriterion {
+        match (self.time_budget, self.target_noise) {
+            (Some(budget), _) => StoppingCriterion::TimeBudget(budget),
+            (None, Some(noise)) => StoppingCriterion::TargetNoise(noise),
+            (None, None) => StoppingCriterion::Samples,
+        }
+    }
+
+    /// The AOVs to render besides the image.
+    pub fn aovs(&self) -> Vec<Aov> {
+        self.aov
+            .iter()
+            .map(|kind| match kind {
+                AovKind::Depth => Aov::Depth,
+                AovKind::Normal => Aov::Normal,
+                AovKind::Albedo => Aov::Albedo,
+                AovKind::Uv => Aov::Uv,
+                AovKind::ObjectId => Aov::ObjectId,
+                AovKind::MaterialId => Aov::MaterialId,
+                AovKind::Direct => Aov::Direct,
+                AovKind::Indirect => Aov::Indirect,
+                AovKind::Emission => Aov::Emission,
+            })
+            .collect()
+    }
+
+    /// Where to write `aov` if it isn't a layer of the output: next to the output, with the
+    /// name of the AOV before the extension.
+    pub fn aov_path(&self, aov: Aov) -> PathBuf {
+        let mut name = self.output.file_stem().unwrap_or_default().to_os_string();
+        name.push(".");
+        name.push(aov.name());
+        if let Some(extension) = self.output.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+
+        self.output.with_file_name(name)
+    }
+
+    /// How to map the render into the displayable range.
+    pub const fn tone_mapper(&self) -> ToneMapper {
+        let operator = match self.tone_map {
+            ToneMapKind::Clamp => ToneMapOperator::Clamp,
+            ToneMapKind::Reinhard => ToneMapOperator::Reinhard,
+            ToneMapKind::ExtendedReinhard => ToneMapOperator::ExtendedReinhard {
+                white: self.white_point,
+            },
+            ToneMapKind::Aces => ToneMapOperator::Aces,
+            ToneMapKind::Agx => ToneMapOperator::AgX,
+            ToneMapKind::Hable => ToneMapOperator::Hable,
+        };
+
+        ToneMapper::new(operator).with_exposure(self.exposure)
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.format
+            .unwrap_or_else(|| match self.output.extension().and_then(OsStr::to_str) {
+                Some("ppm") => OutputFormat::Ppm,
+                Some("exr") => OutputFormat::Exr,
+                Some("hdr") => OutputFormat::Hdr,
+                Some("pfm") => OutputFormat::Pfm,
+                _ => OutputFormat::Png,
+            })
+    }
+
+    /// Write the rendered image to the output path in the requested format, along with its
+    /// AOVs, and the sample map if it was asked for.
+    pub fn write(&self, result: &RenderOutput) -> Result<(), ImageError> {
+        let format = self.output_format();
+        if format == OutputFormat::Exr {
+            let layers = result
+                .aovs
+                .iter()
+                .map(|(aov, image)| (aov.name(), image))
+                .collect::<Vec<_>>();
+            result
+                .image
+                .write_exr(&self.output, self.exr_precision(), &layers)?;
+        } else {
+            self.write_image(&result.image, &self.output, format, true)?;
+            for (aov, image) in &result.aovs {
+                self.write_image(image, &self.aov_path(*aov), format, aov.is_color())?;
+            }
+        }
+        if let Some(path) = &self.sample_map {
+            result.sample_map.write_png(path, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write `image` to `path` in `format`.
+    ///
+    /// Only colors in formats that can't hold colors brighter than white are tone mapped and
+    /// encoded, other values like depths are written as they are.
+    fn write_image(
+        &self,
+        image: &Image,
+        path: &Path,
+        format: OutputFormat,
+        color: bool,
+    ) -> Result<(), ImageError> {
+        match format {
+            OutputFormat::Png if color => {
+                image.tone_mapped(&self.tone_mapper()).write_png(path, true)
+            }
+            OutputFormat::Png => image.write_png(path, false),
+            OutputFormat::Ppm if color => {
+                image.tone_mapped(&self.tone_mapper()).write_ppm(path, true)
+            }
+            OutputFormat::Ppm => image.write_ppm(path, false),
+            OutputFormat::Exr => image.write_exr(path, self.exr_precision(), &[]),
+            OutputFormat::Hdr => image.write_hdr(path),
+            OutputFormat::Pfm => image.write_pfm(path),
+        }
+    }
+
+    const fn exr_precision(&self) -> ExrPrecision {
+        if self.exr_float {
+            ExrPrecision::Float
+        } else {
+            ExrPrecision::Half
+        }
+    }
+}
+
+/// Parse a duration in seconds, which must be finite and not negative.
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds = value.parse::<f64>().map_err(|error| error.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|error| error.to_string())
+}
+
+/// Parse a finite number greater than zero.
+fn parse_positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+        Ok(_) => Err("must be a finite number greater than zero".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter;
+    use std::num::NonZeroU32;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use clap::Parser;
+    use raytracer::render::aov::Aov;
+    use raytracer::render::StoppingCriterion;
+    use raytracer::scene_file::SceneFile;
+    use raytracer::tone_map::{ToneMapOperator, ToneMapper};
+
+    use super::{IntegratorKind, Options, OutputFormat, SamplerKind};
+
+    #[test]
+    fn defaults() {
+        let options = Options::parse_from(["raytracer"]);
+
+        assert_eq!(options.scene_path(), PathBuf::from("scenes/mis.ron"));
+        assert_eq!(
+            options.target().expect("invalid resolution").resolution(),
+            (512, 512)
+        );
+        assert_eq!(options.output_format(), OutputFormat::Png);
+        assert_eq!(options.integrator, IntegratorKind::Path);
+        assert_eq!(options.sampler, SamplerKind::Sobol);
+        assert_eq!(options.stopping_criterion(), StoppingCriterion::Samples);
+        assert_eq!(options.adaptive, None);
+        assert_eq!(options.tone_mapper(), ToneMapper::default());
+        assert!(options.aovs().is_empty());
+    }
+
+    #[test]
+    fn aovs() {
+        let options = Options::parse_from([
+            "raytracer",
+            "-o",
+            "output/result.png",
+            "--aov",
+            "depth,object-id",
+            "--aov",
+            "direct",
+        ]);
+
+        assert_eq!(options.aovs(), [Aov::Depth, Aov::ObjectId, Aov::Direct]);
+        assert_eq!(
+            options.aov_path(Aov::ObjectId),
+            PathBuf::from("output/result.object_id.png")
+        );
+    }
+
+    #[test]
+    fn tone_mapper() {
+        let options = Options::parse_from([
+            "raytracer",
+            "--tone-map",
+            "extended-reinhard",
+            "--white-point",
+            "8",
+            "--exposure",
+            "-1.5",
+        ]);
+
+        assert_eq!(
+            options.tone_mapper(),
+            ToneMapper::new(ToneMapOperator::ExtendedReinhard { white: 8.0 }).with_exposure(-1.5)
+        );
+    }
+
+    #[test]
+    fn invalid_white_point() {
+        for value in ["0", "-4", "inf"] {
+            assert!(Options::try_parse_from(["raytracer", "--white-point", value]).is_err());
+        }
+    }
+
+    #[test]
+    fn seed() {
+        let scene = || SceneFile::load("scenes/mis.ron").expect("could not load scene");
+
+        let options = Options::parse_from(["raytracer"]);
+        assert_eq!(options.override_scene(scene()).seed(), 0);
+
+        let options = Options::parse_from(["raytracer", "--seed", "42"]);
+        assert_eq!(options.override_scene(scene()).seed(), 42);
+    }
+
+    #[test]
+    fn adaptive() {
+        let options = Options::parse_from(["raytracer", "--adaptive"]);
+        assert_eq!(options.adaptive, Some(16));
+
+        let options = Options::parse_from(["raytracer", "--adaptive", "4"]);
+        assert_eq!(options.adaptive, Some(4));
+    }
+
+    #[test]
+    fn stopping_criterion() {
+        let options = Options::parse_from(["raytracer", "--time-budget", "1.5"]);
+        assert_eq!(
+            options.stopping_criterion(),
+            StoppingCriterion::TimeBudget(Duration::from_millis(1500))
+        );
+
+        let options = Options::parse_from(["raytracer", "--target-noise", "0.02"]);
+        assert_eq!(
+            options.stopping_criterion(),
+            StoppingCriterion::TargetNoise(0.02)
+        );
+
+        assert!(Options::try_parse_from([
+            "raytracer",
+            "--time-budget",
+            "1",
+            "--target-noise",
+            "0.02"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn invalid_stopping_criterion() {
+        for (option, value) in [
+            ("--time-budget", "-1"),
+            ("--time-budget", "inf"),
+            ("--time-budget", "1e30"),
+            ("--time-budget", "NaN"),
+            ("--target-noise", "0"),
+            ("--target-noise", "-0.01"),
+            ("--target-noise", "inf"),
+        ] {
+            assert!(
+                Options::try_parse_from(["raytracer", option, value]).is_err(),
+                "{option} {value} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn resolution() {
+        let resolution = |args: &[&str]| {
+            Options::parse_from(iter::once(&"raytracer").chain(args))
+                .target()
+                .map(|image| image.resolution())
+        };
+
+        assert_eq!(resolution(&["-w", "320", "-a", "2"]).ok(), Some((320, 160)));
+        assert_eq!(
+            resolution(&["-w", "320", "-a", "2", "--height", "90"]).ok(),
+            Some((320, 90))
+        );
+        assert!(resolution(&["-a", "1e-9"]).is_err());
+        assert!(resolution(&["-w", "100000", "--height", "100000"]).is_err());
+        assert!(Options::try_parse_from(["raytracer", "-a", "0"]).is_err());
+    }
+
+    #[test]
+    fn samples() {
+        let options = Options::parse_from(["raytracer", "--samples", "10"]);
+        assert_eq!(options.samples.map(NonZeroU32::get), Some(10));
+
+        assert!(Options::try_parse_from(["raytracer", "--samples", "0"]).is_err());
+    }
+
+    #[test]
+    fn scene_selection() {
+        let options = Options::parse_from(["raytracer", "--scene", "cornell_box"]);
+        assert_eq!(
+            options.scene_path(),
+            PathBuf::from("scenes/cornell_box.ron")
+        );
+
+        let options = Options::parse_from(["raytracer", "--scene-file", "my/scene.ron"]);
+        assert_eq!(options.scene_path(), PathBuf::from("my/scene.ron"));
+
+        assert!(Options::try_parse_from(["raytracer", "-s", "mis", "-f", "my/scene.ron"]).is_err());
+    }
+
+    #[test]
+    fn format_from_extension() {
+        let options = Options::parse_from(["raytracer", "-o", "result.ppm"]);
+        assert_eq!(options.output_format(), OutputFormat::Ppm);
+
+        let options = Options::parse_from(["raytracer", "-o", "result.ppm", "--format", "png"]);
+        assert_eq!(options.output_format(), OutputFormat::Png);
+
+        let options = Options::parse_from(["raytracer", "-o", "result.exr"]);
+        assert_eq!(options.output_format(), OutputFormat::Exr);
+
+        let options = Options::parse_from(["raytracer", "-o", "result.pfm"]);
+        assert_eq!(options.output_format(), OutputFormat::Pfm);
+    }
+}
@@ -1,3 +1,5 @@
+//! Linear RGB colors.
+
 use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
@@ -6,10 +8,11 @@ use image::Rgb;
 
 use crate::vec::Vec3;
 
+pub mod space;
+
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Color(Vec3);
 
-#[allow(dead_code)]
 impl Color {
     pub const fn new(r: f64, g: f64, b: f64) -> Self {
         Self(Vec3(r, g, b))
@@ -51,7 +54,17 @@ impl Color {
         self.0 .2
     }
 
-    pub fn clamped(&self) -> Self {
+    /// The largest of the three channels.
+    pub const fn max_component(&self) -> f64 {
+        self.0 .0.max(self.0 .1).max(self.0 .2)
+    }
+
+    /// The brightness of the color as perceived by the human eye, using the Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126f64.mul_add(self.0 .0, 0.7152f64.mul_add(self.0 .1, 0.0722 * self.0 .2))
+    }
+
+    pub const fn clamped(&self) -> Self {
         Self(Vec3(
             self.0 .0.clamp(0.0, 1.0),
             self.0 .1.clamp(0.0, 1.0),
@@ -59,14 +72,29 @@ impl Color {
         ))
     }
 
-    pub fn clamp(&mut self) {
+    pub const fn clamp(&mut self) {
         self.0 .0 = self.0 .0.clamp(0.0, 1.0);
         self.0 .1 = self.0 .1.clamp(0.0, 1.0);
         self.0 .2 = self.0 .2.clamp(0.0, 1.0);
     }
 
-    pub fn to_gamma_space(self) -> Self {
-        Self(Vec3(self.0 .0.sqrt(), self.0 .1.sqrt(), self.0 .2.sqrt()))
+    /// Encode the linear color with the sRGB transfer function, as expected by displays and 8-bit
+    /// image files.
+    pub fn encode_srgb(self) -> Self {
+        Self(Vec3(
+            encode_srgb(self.0 .0),
+            encode_srgb(self.0 .1),
+            encode_srgb(self.0 .2),
+        ))
+    }
+
+    /// Decode a color encoded with the sRGB transfer function back to linear values.
+    pub fn decode_srgb(self) -> Self {
+        Self(Vec3(
+            decode_srgb(self.0 .0),
+            decode_srgb(self.0 .1),
+            decode_srgb(self.0 .2),
+        ))
     }
 
     pub fn as_bytes(&self) -> [u8; 3] {
@@ -79,6 +107,23 @@ impl Color {
     }
 }
 
+/// The piecewise sRGB transfer function, linear near black and a 2.4 power curve above.
+fn encode_srgb(value: f64) -> f64 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055f64.mul_add(value.powf(1.0 / 2.4), -0.055)
+    }
+}
+
+fn decode_srgb(value: f64) -> f64 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 impl Add for Color {
     type Output = Self;
 
@@ -180,6 +225,12 @@ impl From<(u8, u8, u8)> for Color {
     }
 }
 
+impl From<(f64, f64, f64)> for Color {
+    fn from(value: (f64, f64, f64)) -> Self {
+        Self::new(value.0, value.1, value.2)
+    }
+}
+
 impl From<Vec3> for Color {
     fn from(value: Vec3) -> Self {
         Self(value)
@@ -251,6 +302,23 @@ mod tests {
         assert_abs_diff_eq!(c1, Color::new(1.0, 0.0, 0.4));
     }
 
+    #[test]
+    fn srgb() {
+        let color = Color::new(0.0, 0.002, 0.5);
+
+        assert_abs_diff_eq!(
+            color.encode_srgb(),
+            Color::new(0.0, 0.025_84, 0.735_357),
+            epsilon = 1e-6
+        );
+        assert_abs_diff_eq!(color.encode_srgb().decode_srgb(), color, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            Color::white().encode_srgb(),
+            Color::white(),
+            epsilon = 1e-12
+        );
+    }
+
     #[test]
     fn addition() {
         let mut c1 = Color::new(1.0, 0.5, 0.1);
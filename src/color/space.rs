@@ -0,0 +1,137 @@
+//! Converting colors between the ways they are stored and the space the renderer works in.
+
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::color::Color;
+
+/// Linear sRGB to `ACEScg`, adapting the D65 white point of sRGB to the D60 one of ACES with the
+/// Bradford transform.
+const LINEAR_SRGB_TO_ACESCG: [[f64; 3]; 3] = [
+    [0.613_097_402, 0.339_523_146, 0.047_379_451],
+    [0.070_193_722, 0.916_353_879, 0.013_452_398],
+    [0.020_615_593, 0.109_569_773, 0.869_814_634],
+];
+/// The inverse of [`LINEAR_SRGB_TO_ACESCG`].
+const ACESCG_TO_LINEAR_SRGB: [[f64; 3]; 3] = [
+    [1.705_050_993, -0.621_792_121, -0.083_258_872],
+    [-0.130_256_418, 1.140_804_737, -0.010_548_319],
+    [-0.024_003_357, -0.128_968_976, 1.152_972_333],
+];
+
+/// The color space light is transported in.
+///
+/// Colors given to the renderer, e.g. in scene files, are always linear sRGB and converted into
+/// the working space when the scene is built, and renders are converted back to linear sRGB. The
+/// wider gamut of `ACEScg` makes the products of saturated colors after many bounces closer to
+/// what a spectral renderer would get.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WorkingSpace {
+    #[default]
+    LinearSrgb,
+    AcesCg,
+}
+
+impl WorkingSpace {
+    /// Convert a linear sRGB color into this working space.
+    pub fn from_linear_srgb(self, color: Color) -> Color {
+        match self {
+            Self::LinearSrgb => color,
+            Self::AcesCg => transform(&LINEAR_SRGB_TO_ACESCG, color),
+        }
+    }
+
+    /// Convert a color in this working space into linear sRGB.
+    pub fn to_linear_srgb(self, color: Color) -> Color {
+        match self {
+            Self::LinearSrgb => color,
+            Self::AcesCg => transform(&ACESCG_TO_LINEAR_SRGB, color),
+        }
+    }
+}
+
+/// How the values of an image texture are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Colors encoded with the sRGB transfer function, like almost every 8-bit image.
+    Srgb,
+    /// Linear colors with the primaries of sRGB, like HDR and floating point images.
+    LinearSrgb,
+    /// Values that aren't colors, e.g. roughness, which are used exactly as they are stored.
+    Data,
+}
+
+impl ColorSpace {
+    /// The color space colors in the image file at `path` are most likely stored in, judging by
+    /// whether its format stores floating point values.
+    pub fn for_file<P: AsRef<Path>>(path: P) -> Self {
+        let extension = path.as_ref().extension().and_then(OsStr::to_str);
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("hdr" | "pfm" | "exr") => Self::LinearSrgb,
+            _ => Self::Srgb,
+        }
+    }
+
+    /// Convert a value stored in this color space into `working_space`.
+    pub fn decode(self, color: Color, working_space: WorkingSpace) -> Color {
+        match self {
+            Self::Srgb => working_space.from_linear_srgb(color.decode_srgb()),
+            Self::LinearSrgb => working_space.from_linear_srgb(color),
+            Self::Data => color,
+        }
+    }
+}
+
+/// Multiply `color` with `matrix`, given row by row.
+pub(crate) fn transform(matrix: &[[f64; 3]; 3], color: Color) -> Color {
+    let [r, g, b] =
+        matrix.map(|row| row[0].mul_add(color.r(), row[1].mul_add(color.g(), row[2] * color.b())));
+
+    Color::new(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+
+    use super::{ColorSpace, WorkingSpace};
+
+    #[test]
+    fn acescg_round_trip() {
+        let color = Color::new(0.9, 0.2, 0.05);
+        let acescg = WorkingSpace::AcesCg.from_linear_srgb(color);
+
+        assert_abs_diff_eq!(
+            WorkingSpace::AcesCg.to_linear_srgb(acescg),
+            color,
+            epsilon = 1e-8
+        );
+        // white stays white, but saturated colors become less saturated in the wider gamut
+        assert_abs_diff_eq!(
+            WorkingSpace::AcesCg.from_linear_srgb(Color::white()),
+            Color::white(),
+            epsilon = 1e-8
+        );
+        assert!(acescg.r() < color.r() && acescg.b() > color.b());
+    }
+
+    #[test]
+    fn decode() {
+        let stored = Color::new(0.5, 0.5, 0.5);
+
+        assert_abs_diff_eq!(
+            ColorSpace::Srgb.decode(stored, WorkingSpace::LinearSrgb),
+            Color::new(0.214_041, 0.214_041, 0.214_041),
+            epsilon = 1e-6
+        );
+        assert_eq!(
+            ColorSpace::Data.decode(Color::red(), WorkingSpace::AcesCg),
+            Color::red()
+        );
+        assert_eq!(ColorSpace::for_file("sky.hdr"), ColorSpace::LinearSrgb);
+        assert_eq!(ColorSpace::for_file("sky.EXR"), ColorSpace::LinearSrgb);
+        assert_eq!(ColorSpace::for_file("earth.png"), ColorSpace::Srgb);
+    }
+}
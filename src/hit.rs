@@ -1,3 +1,5 @@
+//! Objects that rays can hit and structures to organise them.
+
 use std::sync::Arc;
 
 use crate::material::Material;
@@ -8,11 +10,16 @@ use crate::vec::Vec3;
 
 pub mod r#box;
 pub mod bvh;
+pub mod id;
+pub mod lights;
+pub mod mesh;
 pub mod quad;
 pub mod scene;
 pub mod sphere;
 pub mod transform;
+pub mod triangle;
 
+/// Where and how a ray hit an object.
 pub struct HitResult {
     pub t: f64,
     pub u: f64,
@@ -21,6 +28,9 @@ pub struct HitResult {
     pub normal: Vec3,
     pub material: Arc<dyn Material>,
     pub front_face: bool,
+    /// The ID of the object in the object ID AOV, 0 unless it was given one with
+    /// [`ObjectId`](id::ObjectId).
+    pub object_id: u32,
 }
 
 impl HitResult {
@@ -47,12 +57,49 @@ impl HitResult {
             },
             material,
             front_face,
+            object_id: 0,
         }
     }
 }
 
+/// How far from their origin rays start to hit objects, so they don't hit the surface they leave
+/// from.
+pub const BIAS: f64 = 0.001;
+
+/// A point sampled on the surface of an object.
+pub struct SurfaceSample {
+    pub point: Vec3,
+    /// The probability density of sampling the direction towards the point, with respect to
+    /// solid angle as seen from the origin the sample was taken for.
+    pub pdf: f64,
+}
+
+/// An object that can be intersected by a ray.
 pub trait Hit: Send + Sync {
     fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult>;
 
+    /// Whether the ray hits anything at all within `t_interval`.
+    ///
+    /// Unlike [`Hit::hit`], this doesn't need to find the closest hit, so implementations can
+    /// stop at the first one they find.
+    fn occluded(&self, ray: &Ray, t_interval: Interval) -> bool {
+        self.hit(ray, t_interval).is_some()
+    }
+
     fn bounding_box(&self) -> &Aabb;
+
+    /// Sample a point on the surface of the object as seen from `origin`, so the object can be
+    /// used as a light source.
+    ///
+    /// `u` are two independent random numbers in `[0, 1)`. Returns `None` if the object doesn't
+    /// support sampling or can't be seen from `origin`.
+    fn sample(&self, _origin: &Vec3, _time: f64, _u: (f64, f64)) -> Option<SurfaceSample> {
+        None
+    }
+
+    /// The probability density of [`Hit::sample`] choosing a point in the direction of `ray` as
+    /// seen from its origin, with respect to solid angle.
+    fn pdf(&self, _ray: &Ray) -> f64 {
+        0.0
+    }
 }
@@ -1,104 +1,359 @@
-use std::cmp::Ordering;
 use std::sync::Arc;
 
-use rand::Rng;
-
+use crate::hit::bvh::flat::FlatBvh;
 use crate::hit::{Hit, HitResult};
 use crate::math::aabb::Aabb;
 use crate::math::interval::Interval;
 use crate::ray::Ray;
 
+/// Number of buckets the objects are sorted into along each axis when looking for a split.
+const BIN_COUNT: usize = 16;
+/// Cost of visiting a node, relative to the cost of intersecting an object.
+const TRAVERSAL_COST: f64 = 0.125;
+/// Nodes this deep in the hierarchy become leaves, no matter how many objects they contain.
+const MAX_DEPTH: usize = 64;
+
+pub mod flat;
+
+pub struct NodeBuilder {
+    objects: Vec<Arc<dyn Hit>>,
+    max_leaf_size: usize,
+}
+
+impl NodeBuilder {
+    pub fn new(objects: Vec<Arc<dyn Hit>>) -> Self {
+        Self {
+            objects,
+            max_leaf_size: 1,
+        }
+    }
+
+    /// Allow up to `max_leaf_size` objects in a leaf.
+    ///
+    /// Nodes with at most this many objects are only split if the surface area heuristic expects
+    /// the split to be cheaper to traverse than testing all of the objects.
+    pub fn with_max_leaf_size(mut self, max_leaf_size: usize) -> Self {
+        self.max_leaf_size = max_leaf_size.max(1);
+
+        self
+    }
+
+    /// Build the hierarchy top-down, splitting every node where the surface area heuristic
+    /// predicts the lowest cost.
+    ///
+    /// The objects are binned by the centroids of their bounding boxes, so the same objects in
+    /// the same order always produce the same tree.
+    pub fn build(self) -> Node {
+        Node::build(self.objects, self.max_leaf_size, 0)
+    }
+
+    /// Build the hierarchy like [`NodeBuilder::build`] and flatten it for faster traversal.
+    pub fn build_flat(self) -> FlatBvh {
+        FlatBvh::from(self.build())
+    }
+}
+
+/// A bounding volume hierarchy over a list of objects.
 pub struct Node {
-    left: Arc<dyn Hit>,
-    right: Arc<dyn Hit>,
+    children: Children,
     bounding_box: Aabb,
 }
 
+enum Children {
+    Leaf(Vec<Arc<dyn Hit>>),
+    Split {
+        left: Box<Node>,
+        right: Box<Node>,
+        axis: u32,
+    },
+}
+
+/// Where to split the objects of a node.
+struct Split {
+    axis: u32,
+    centroids: Interval,
+    bin: usize,
+    cost: f64,
+}
+
 impl Node {
-    pub fn new(mut objects: Vec<Arc<dyn Hit>>) -> Self {
-        let axis = rand::thread_rng().gen_range(0..3);
-        let comparator = match axis {
-            0 => Self::box_compare_x,
-            1 => Self::box_compare_y,
-            2 => Self::box_compare_z,
-            _ => unreachable!(),
-        };
-        let left;
-        let right;
+    /// Build a hierarchy with one object per leaf, see [`NodeBuilder`].
+    pub fn new(objects: Vec<Arc<dyn Hit>>) -> Self {
+        NodeBuilder::new(objects).build()
+    }
+
+    fn build(objects: Vec<Arc<dyn Hit>>, max_leaf_size: usize, depth: usize) -> Self {
+        let bounding_box = objects.iter().fold(Aabb::empty(), |bounding_box, object| {
+            bounding_box.combined(object.bounding_box())
+        });
+        if objects.len() <= 1 || depth >= MAX_DEPTH {
+            return Self {
+                children: Children::Leaf(objects),
+                bounding_box,
+            };
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let leaf_cost = objects.len() as f64;
+        let must_split = objects.len() > max_leaf_size;
+        let (left, right, axis) = match Self::find_split(&objects, &bounding_box) {
+            Some(split) if must_split || split.cost < leaf_cost => {
+                let (left, right) = objects.into_iter().partition(|object| {
+                    bin(object.as_ref(), split.axis, &split.centroids) <= split.bin
+                });
 
-        match objects.len() {
-            1 => {
-                left = objects.swap_remove(0);
-                right = left.clone();
+                (left, right, split.axis)
             }
-            2 => {
-                let first = objects.swap_remove(0);
-                let second = objects.swap_remove(0);
-
-                if comparator(&first, &second) == Ordering::Less {
-                    left = first;
-                    right = second;
-                } else {
-                    left = second;
-                    right = first;
-                }
+            // all centroids are in the same place, so any split is as good as another
+            None if must_split => {
+                let mut left = objects;
+                let right = left.split_off(left.len() / 2);
+
+                (left, right, 0)
             }
             _ => {
-                objects.sort_unstable_by(comparator);
-
-                right = Arc::new(Self::new(objects.split_off(objects.len() / 2)));
-                left = Arc::new(Self::new(objects));
+                return Self {
+                    children: Children::Leaf(objects),
+                    bounding_box,
+                }
             }
-        }
+        };
 
         Self {
-            bounding_box: left.bounding_box().combined(right.bounding_box()),
-            left,
-            right,
+            children: Children::Split {
+                left: Box::new(Self::build(left, max_leaf_size, depth + 1)),
+                right: Box::new(Self::build(right, max_leaf_size, depth + 1)),
+                axis,
+            },
+            bounding_box,
         }
     }
 
-    fn box_compare(a: &Arc<dyn Hit>, b: &Arc<dyn Hit>, axis: u32) -> Ordering {
-        a.bounding_box()
-            .axis(axis)
-            .start()
-            .total_cmp(&b.bounding_box().axis(axis).start())
-    }
+    /// Find the split between two bins with the lowest surface area heuristic cost.
+    ///
+    /// Returns `None` if the objects can't be separated by their centroids.
+    fn find_split(objects: &[Arc<dyn Hit>], bounding_box: &Aabb) -> Option<Split> {
+        let centroids = objects.iter().fold(Aabb::empty(), |centroids, object| {
+            let centroid = object.bounding_box().centroid();
 
-    fn box_compare_x(a: &Arc<dyn Hit>, b: &Arc<dyn Hit>) -> Ordering {
-        Self::box_compare(a, b, 0)
-    }
+            centroids.combined(&Aabb::with_extrema(centroid, centroid))
+        });
+        let area = bounding_box.surface_area().max(f64::MIN_POSITIVE);
+        let mut best: Option<Split> = None;
 
-    fn box_compare_y(a: &Arc<dyn Hit>, b: &Arc<dyn Hit>) -> Ordering {
-        Self::box_compare(a, b, 1)
-    }
+        for axis in 0..3 {
+            let centroids = centroids.axis(axis);
+            if centroids.len() <= 0.0 {
+                continue;
+            }
+
+            let mut bins = vec![(Aabb::empty(), 0_usize); BIN_COUNT];
+            for object in objects {
+                let (bounding_box, count) = &mut bins[bin(object.as_ref(), axis, centroids)];
+                bounding_box.combine(object.bounding_box());
+                *count += 1;
+            }
+
+            // the cost of everything right of each split, swept from the right
+            let mut right = vec![(0.0, 0); BIN_COUNT - 1];
+            let mut right_box = Aabb::empty();
+            let mut right_count = 0;
+            for i in (1..BIN_COUNT).rev() {
+                right_box.combine(&bins[i].0);
+                right_count += bins[i].1;
+                right[i - 1] = (right_box.surface_area(), right_count);
+            }
+
+            let mut left_box = Aabb::empty();
+            let mut left_count = 0;
+            for (i, &(right_area, right_count)) in right.iter().enumerate() {
+                left_box.combine(&bins[i].0);
+                left_count += bins[i].1;
+                if left_count == 0 || right_count == 0 {
+                    continue;
+                }
+
+                #[allow(clippy::cast_precision_loss)]
+                let cost = TRAVERSAL_COST
+                    + left_box
+                        .surface_area()
+                        .mul_add(left_count as f64, right_area * right_count as f64)
+                        / area;
+                if best.as_ref().is_none_or(|best| cost < best.cost) {
+                    best = Some(Split {
+                        axis,
+                        centroids: centroids.clone(),
+                        bin: i,
+                        cost,
+                    });
+                }
+            }
+        }
 
-    fn box_compare_z(a: &Arc<dyn Hit>, b: &Arc<dyn Hit>) -> Ordering {
-        Self::box_compare(a, b, 2)
+        best
     }
 }
 
+/// The bin the centroid of an object falls into along `axis`.
+fn bin(object: &dyn Hit, axis: u32, centroids: &Interval) -> usize {
+    let offset =
+        (object.bounding_box().centroid().axis(axis) - centroids.start()) / centroids.len();
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    ((offset * BIN_COUNT as f64) as usize).min(BIN_COUNT - 1)
+}
+
 impl Hit for Node {
     fn hit(&self, ray: &Ray, mut t_interval: Interval) -> Option<HitResult> {
         if !self.bounding_box.hit(ray, &mut t_interval) {
             return None;
         }
 
-        let left = self.left.hit(ray, t_interval.clone());
-        let right = self.right.hit(
-            ray,
-            Interval(
-                t_interval.start()
-                    ..left
-                        .as_ref()
-                        .map_or_else(|| t_interval.end(), |left| left.t),
-            ),
-        );
-
-        right.or(left)
+        match &self.children {
+            Children::Leaf(objects) => {
+                let mut closest_hit: Option<HitResult> = None;
+
+                for object in objects {
+                    if let Some(hit) = object.hit(
+                        ray,
+                        Interval(
+                            t_interval.start()
+                                ..closest_hit
+                                    .as_ref()
+                                    .map_or_else(|| t_interval.end(), |hit| hit.t),
+                        ),
+                    ) {
+                        closest_hit = Some(hit);
+                    }
+                }
+
+                closest_hit
+            }
+            Children::Split { left, right, .. } => {
+                let left = left.hit(ray, t_interval.clone());
+                let right = right.hit(
+                    ray,
+                    Interval(
+                        t_interval.start()
+                            ..left
+                                .as_ref()
+                                .map_or_else(|| t_interval.end(), |left| left.t),
+                    ),
+                );
+
+                right.or(left)
+            }
+        }
     }
 
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::sphere::SphereBuilder;
+    use crate::hit::Hit;
+    use crate::material::lambertian::Lambertian;
+    use crate::math::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::{Children, Node, NodeBuilder};
+
+    fn spheres(centers: &[f64]) -> Vec<Arc<dyn Hit>> {
+        let material = Arc::new(Lambertian::colored(Color::white()));
+
+        centers
+            .iter()
+            .map(|&x| {
+                Arc::new(SphereBuilder::new(Vec3(x, 0.0, 0.0), 0.25, material.clone()).build())
+                    as Arc<dyn Hit>
+            })
+            .collect()
+    }
+
+    /// The number of objects in every leaf, from left to right.
+    fn leaf_sizes(node: &Node) -> Vec<usize> {
+        match &node.children {
+            Children::Leaf(objects) => vec![objects.len()],
+            Children::Split { left, right, .. } => {
+                let mut sizes = leaf_sizes(left);
+                sizes.extend(leaf_sizes(right));
+
+                sizes
+            }
+        }
+    }
+
+    #[test]
+    fn closest_hit() {
+        let node = Node::new(spheres(&[3.0, 0.0, 5.0, 1.0, 4.0, 2.0]));
+        let hit = node
+            .hit(
+                &Ray {
+                    origin: Vec3(-10.0, 0.0, 0.0),
+                    direction: Vec3(1.0, 0.0, 0.0),
+                    time: 0.0,
+                },
+                Interval(0.0..f64::INFINITY),
+            )
+            .expect("ray should hit the first sphere");
+
+        assert_abs_diff_eq!(hit.point, Vec3(-0.25, 0.0, 0.0));
+        assert_eq!(leaf_sizes(&node), vec![1; 6]);
+    }
+
+    #[test]
+    fn deterministic() {
+        let centers = [0.0, 0.1, 0.3, 7.0, 7.5, 2.0, 2.2, 9.0, 0.2, 3.0];
+        let first = NodeBuilder::new(spheres(&centers))
+            .with_max_leaf_size(3)
+            .build();
+        let second = NodeBuilder::new(spheres(&centers))
+            .with_max_leaf_size(3)
+            .build();
+
+        assert_eq!(leaf_sizes(&first), leaf_sizes(&second));
+        assert!(leaf_sizes(&first).iter().all(|&size| size <= 3));
+    }
+
+    #[test]
+    fn isolates_outliers() {
+        let mut centers = vec![100.0];
+        centers.extend((0..9).map(f64::from));
+        let node = Node::new(spheres(&centers));
+
+        let Children::Split { left, right, .. } = &node.children else {
+            panic!("root should be split");
+        };
+        assert_eq!(leaf_sizes(left).len(), 9);
+        assert_eq!(leaf_sizes(right), vec![1]);
+    }
+
+    #[test]
+    fn empty() {
+        let node = Node::new(Vec::new());
+
+        assert!(node
+            .hit(
+                &Ray {
+                    origin: Vec3(0.0, 0.0, 0.0),
+                    direction: Vec3(1.0, 0.0, 0.0),
+                    time: 0.0,
+                },
+                Interval(0.0..f64::INFINITY),
+            )
+            .is_none());
+    }
+}
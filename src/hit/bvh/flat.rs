@@ -0,0 +1,270 @@
+use std::sync::Arc;
+
+use crate::hit::bvh::{Children, Node, NodeBuilder, MAX_DEPTH};
+use crate::hit::{Hit, HitResult};
+use crate::math::aabb::Aabb;
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+
+/// A bounding volume hierarchy stored in a single array and traversed without recursion.
+///
+/// The nodes are laid out depth-first, so the first child of a node directly follows it. Rays
+/// visit the child on their side of the split first and skip everything behind the closest hit
+/// found so far.
+pub struct FlatBvh {
+    nodes: Vec<FlatNode>,
+    objects: Vec<Arc<dyn Hit>>,
+}
+
+/// A node of a [`FlatBvh`], sized to fit into a cache line.
+///
+/// For interior nodes, `offset` is the index of the second child. For leaves, it is the index of
+/// the first of their `count` objects, which may be none.
+struct FlatNode {
+    bounding_box: Aabb,
+    offset: u32,
+    count: u32,
+    axis: u8,
+    leaf: bool,
+}
+
+impl FlatBvh {
+    /// Build a hierarchy with one object per leaf, see [`NodeBuilder`].
+    pub fn new(objects: Vec<Arc<dyn Hit>>) -> Self {
+        NodeBuilder::new(objects).build_flat()
+    }
+
+    fn flatten(&mut self, node: Node) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(FlatNode {
+            bounding_box: node.bounding_box,
+            offset: 0,
+            count: 0,
+            axis: 0,
+            leaf: false,
+        });
+
+        match node.children {
+            Children::Leaf(objects) => {
+                self.nodes[index].offset = to_u32(self.objects.len());
+                self.nodes[index].count = to_u32(objects.len());
+                self.nodes[index].leaf = true;
+                self.objects.extend(objects);
+            }
+            Children::Split { left, right, axis } => {
+                self.flatten(*left);
+                self.nodes[index].offset = to_u32(self.flatten(*right));
+                self.nodes[index].axis = u8::try_from(axis).expect("invalid axis");
+            }
+        }
+
+        index
+    }
+
+    /// Walk the hierarchy front to back and call `visit` for every object in a leaf the ray
+    /// passes through.
+    ///
+    /// `visit` returns the distance of a hit if there was one, which shortens the ray for the rest
+    /// of the traversal. If `first_hit` is set, the traversal stops at the first hit instead.
+    fn traverse<F>(&self, ray: &Ray, t_interval: &Interval, first_hit: bool, mut visit: F)
+    where
+        F: FnMut(&dyn Hit, Interval) -> Option<f64>,
+    {
+        let direction_negative = [
+            ray.direction.0 < 0.0,
+            ray.direction.1 < 0.0,
+            ray.direction.2 < 0.0,
+        ];
+        let mut t_end = t_interval.end();
+        let mut stack = [0; MAX_DEPTH];
+        let mut stack_len = 0;
+        let mut index = 0;
+
+        loop {
+            let node = &self.nodes[index];
+
+            if node
+                .bounding_box
+                .hit(ray, &mut Interval(t_interval.start()..t_end))
+            {
+                if node.leaf {
+                    let start = node.offset as usize;
+                    for object in &self.objects[start..start + node.count as usize] {
+                        if let Some(t) = visit(object.as_ref(), Interval(t_interval.start()..t_end))
+                        {
+                            if first_hit {
+                                return;
+                            }
+                            t_end = t;
+                        }
+                    }
+                } else {
+                    // continue with the near child and come back to the far one later
+                    let (near, far) = if direction_negative[node.axis as usize] {
+                        (node.offset as usize, index + 1)
+                    } else {
+                        (index + 1, node.offset as usize)
+                    };
+                    stack[stack_len] = far;
+                    stack_len += 1;
+                    index = near;
+
+                    continue;
+                }
+            }
+
+            if stack_len == 0 {
+                return;
+            }
+            stack_len -= 1;
+            index = stack[stack_len];
+        }
+    }
+}
+
+impl From<Node> for FlatBvh {
+    fn from(node: Node) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::new(),
+            objects: Vec::new(),
+        };
+        bvh.flatten(node);
+
+        bvh
+    }
+}
+
+impl Hit for FlatBvh {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        let mut closest_hit = None;
+
+        self.traverse(ray, &t_interval, false, |object, t_interval| {
+            let hit = object.hit(ray, t_interval)?;
+            let t = hit.t;
+            closest_hit = Some(hit);
+
+            Some(t)
+        });
+
+        closest_hit
+    }
+
+    fn occluded(&self, ray: &Ray, t_interval: Interval) -> bool {
+        let mut occluded = false;
+
+        self.traverse(ray, &t_interval, true, |object, t_interval| {
+            occluded = object.occluded(ray, t_interval.clone());
+
+            occluded.then(|| t_interval.start())
+        });
+
+        occluded
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.nodes[0].bounding_box
+    }
+}
+
+fn to_u32(index: usize) -> u32 {
+    u32::try_from(index).expect("too many nodes in the hierarchy")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::color::Color;
+    use crate::hit::bvh::{Children, Node, NodeBuilder};
+    use crate::hit::sphere::SphereBuilder;
+    use crate::hit::Hit;
+    use crate::material::lambertian::Lambertian;
+    use crate::math::aabb::Aabb;
+    use crate::math::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::FlatBvh;
+
+    fn random_spheres(rng: &mut StdRng, count: usize) -> Vec<Arc<dyn Hit>> {
+        let material = Arc::new(Lambertian::colored(Color::white()));
+
+        (0..count)
+            .map(|_| {
+                let center = Vec3(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                );
+
+                Arc::new(
+                    SphereBuilder::new(center, rng.gen_range(0.1..1.0), material.clone()).build(),
+                ) as Arc<dyn Hit>
+            })
+            .collect()
+    }
+
+    fn random_ray(rng: &mut StdRng) -> Ray {
+        Ray {
+            origin: Vec3(
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+                rng.gen_range(-15.0..15.0),
+            ),
+            direction: Vec3(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            ),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn same_hits_as_tree() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let spheres = random_spheres(&mut rng, 200);
+        let tree = Node::new(spheres.clone());
+        let flat = NodeBuilder::new(spheres).with_max_leaf_size(4).build_flat();
+
+        for _ in 0..1000 {
+            let ray = random_ray(&mut rng);
+            let expected = tree.hit(&ray, Interval(0.001..f64::INFINITY));
+            let actual = flat.hit(&ray, Interval(0.001..f64::INFINITY));
+
+            assert_eq!(expected.is_some(), actual.is_some());
+            assert_eq!(
+                flat.occluded(&ray, Interval(0.001..f64::INFINITY)),
+                actual.is_some()
+            );
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                assert_abs_diff_eq!(expected.t, actual.t);
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = FlatBvh::new(Vec::new());
+        let ray = Ray {
+            origin: Vec3(0.0, 0.0, 0.0),
+            direction: Vec3(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        assert!(bvh.hit(&ray, Interval(0.0..f64::INFINITY)).is_none());
+        assert!(!bvh.occluded(&ray, Interval(0.0..f64::INFINITY)));
+
+        // an empty leaf the ray passes through is still a leaf, not an interior node
+        let bvh = FlatBvh::from(Node {
+            children: Children::Leaf(Vec::new()),
+            bounding_box: Aabb::with_extrema(Vec3(-1.0, -1.0, -1.0), Vec3(1.0, 1.0, 1.0)),
+        });
+        assert!(bvh.hit(&ray, Interval(0.0..f64::INFINITY)).is_none());
+        assert!(!bvh.occluded(&ray, Interval(0.0..f64::INFINITY)));
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use crate::hit::{Hit, HitResult, SurfaceSample};
+use crate::math::aabb::Aabb;
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+/// Gives every hit on an object the same ID, so the object can be told apart from the others in
+/// the object ID AOV.
+///
+/// IDs of objects nested inside of each other are overridden by the outermost one.
+pub struct ObjectId {
+    object: Arc<dyn Hit>,
+    id: u32,
+}
+
+impl ObjectId {
+    pub fn new(object: Arc<dyn Hit>, id: u32) -> Self {
+        Self { object, id }
+    }
+}
+
+impl Hit for ObjectId {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        let mut hit = self.object.hit(ray, t_interval)?;
+        hit.object_id = self.id;
+
+        Some(hit)
+    }
+
+    fn occluded(&self, ray: &Ray, t_interval: Interval) -> bool {
+        self.object.occluded(ray, t_interval)
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        self.object.bounding_box()
+    }
+
+    fn sample(&self, origin: &Vec3, time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        self.object.sample(origin, time, u)
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        self.object.pdf(ray)
+    }
+}
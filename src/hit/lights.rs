@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use crate::hit::{Hit, HitResult, SurfaceSample};
+use crate::math::aabb::Aabb;
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+/// The light sources of a scene, which are sampled directly to find the light arriving at a
+/// surface.
+///
+/// Every light is chosen with the same probability, so the density of a direction is the average
+/// of the densities of all lights.
+pub struct Lights {
+    lights: Vec<Arc<dyn Hit>>,
+    bounding_box: Aabb,
+}
+
+impl Lights {
+    pub fn new(lights: Vec<Arc<dyn Hit>>) -> Self {
+        let bounding_box = lights.iter().fold(Aabb::empty(), |bounding_box, light| {
+            bounding_box.combined(light.bounding_box())
+        });
+
+        Self {
+            lights,
+            bounding_box,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Hit for Lights {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        let mut closest_hit: Option<HitResult> = None;
+
+        for light in &self.lights {
+            if let Some(hit) = light.hit(
+                ray,
+                Interval(
+                    t_interval.start()
+                        ..closest_hit
+                            .as_ref()
+                            .map_or_else(|| t_interval.end(), |hit| hit.t),
+                ),
+            ) {
+                closest_hit = Some(hit);
+            }
+        }
+
+        closest_hit
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+
+    fn sample(&self, origin: &Vec3, time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        // pick a light with the first random number and stretch what is left of it back to [0, 1)
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let (index, u_light) = {
+            let scaled = u.0 * self.lights.len() as f64;
+            let index = (scaled as usize).min(self.lights.len().checked_sub(1)?);
+
+            (index, scaled - index as f64)
+        };
+        let sample = self.lights[index].sample(origin, time, (u_light, u.1))?;
+
+        Some(SurfaceSample {
+            pdf: self.pdf(&Ray {
+                origin: *origin,
+                direction: sample.point - *origin,
+                time,
+            }),
+            ..sample
+        })
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let weight = 1.0 / self.lights.len() as f64;
+
+        self.lights.iter().map(|light| light.pdf(ray)).sum::<f64>() * weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::quad::Quad;
+    use crate::hit::sphere::SphereBuilder;
+    use crate::hit::Hit;
+    use crate::material::light::DiffuseLight;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::Lights;
+
+    #[test]
+    fn sample_and_pdf() {
+        let material = Arc::new(DiffuseLight::colored(Color::white()));
+        let lights = Lights::new(vec![
+            Arc::new(Quad::new(
+                Vec3(-1.0, 2.0, -1.0),
+                Vec3(2.0, 0.0, 0.0),
+                Vec3(0.0, 0.0, 2.0),
+                material.clone(),
+            )),
+            Arc::new(SphereBuilder::new(Vec3(0.0, -3.0, 0.0), 1.0, material).build()),
+        ]);
+        let origin = Vec3::zero();
+
+        let sample = lights
+            .sample(&origin, 0.0, (0.25, 0.5))
+            .expect("the quad should be visible");
+        assert_abs_diff_eq!(sample.point.1, 2.0);
+        // half the chance of picking the quad, seen head-on from two units away
+        assert_abs_diff_eq!(sample.pdf, 0.5 * 4.0 / 4.0);
+
+        let sample = lights
+            .sample(&origin, 0.0, (0.75, 0.5))
+            .expect("the sphere should be visible");
+        assert_abs_diff_eq!((sample.point - Vec3(0.0, -3.0, 0.0)).len(), 1.0);
+
+        let miss = Ray {
+            origin,
+            direction: Vec3(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert_abs_diff_eq!(lights.pdf(&miss), 0.0);
+    }
+}
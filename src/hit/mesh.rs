@@ -0,0 +1,282 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use crate::hit::bvh::flat::FlatBvh;
+use crate::hit::bvh::NodeBuilder;
+use crate::hit::triangle;
+use crate::hit::{Hit, HitResult};
+use crate::material::Material;
+use crate::math::aabb::Aabb;
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+/// Triangles are cheap to intersect, so a few of them share a leaf of the hierarchy.
+const MAX_FACES_PER_LEAF: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MeshError {
+    NoFaces,
+    IndexOutOfBounds { face: usize, index: usize },
+    AttributeCountMismatch { attribute: &'static str },
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoFaces => write!(f, "mesh has no faces"),
+            Self::IndexOutOfBounds { face, index } => {
+                write!(
+                    f,
+                    "face {face} refers to vertex {index}, which does not exist"
+                )
+            }
+            Self::AttributeCountMismatch { attribute } => {
+                write!(
+                    f,
+                    "mesh has a different number of {attribute} than positions"
+                )
+            }
+        }
+    }
+}
+
+/// The vertex buffers of a mesh, shared by all of its faces.
+///
+/// Every vertex has a position and optionally a normal and texture coordinates. Faces are triples
+/// of vertex indices.
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub faces: Vec<[usize; 3]>,
+    pub material: Arc<dyn Material>,
+}
+
+pub struct MeshBuilder {
+    data: MeshData,
+}
+
+impl MeshBuilder {
+    pub fn new(positions: Vec<Vec3>, faces: Vec<[usize; 3]>, material: Arc<dyn Material>) -> Self {
+        Self {
+            data: MeshData {
+                positions,
+                normals: None,
+                uvs: None,
+                faces,
+                material,
+            },
+        }
+    }
+
+    /// Use per-vertex normals for smooth shading instead of the geometric normals of the faces.
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        self.data.normals = Some(normals);
+        self
+    }
+
+    /// Use per-vertex texture coordinates instead of the barycentric coordinates of the faces.
+    pub fn with_uvs(mut self, uvs: Vec<(f64, f64)>) -> Self {
+        self.data.uvs = Some(uvs);
+        self
+    }
+
+    /// Check the vertex buffers and build the acceleration structure over the faces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mesh has no faces, a face refers to a vertex that doesn't exist or
+    /// there is not exactly one normal and texture coordinate per vertex (if there are any).
+    pub fn build(self) -> Result<Mesh, MeshError> {
+        let data = self.data;
+        let vertex_count = data.positions.len();
+
+        if data.faces.is_empty() {
+            return Err(MeshError::NoFaces);
+        }
+        if data
+            .normals
+            .as_ref()
+            .is_some_and(|normals| normals.len() != vertex_count)
+        {
+            return Err(MeshError::AttributeCountMismatch {
+                attribute: "normals",
+            });
+        }
+        if data
+            .uvs
+            .as_ref()
+            .is_some_and(|uvs| uvs.len() != vertex_count)
+        {
+            return Err(MeshError::AttributeCountMismatch {
+                attribute: "texture coordinates",
+            });
+        }
+        for (face, indices) in data.faces.iter().enumerate() {
+            if let Some(&index) = indices.iter().find(|&&index| index >= vertex_count) {
+                return Err(MeshError::IndexOutOfBounds { face, index });
+            }
+        }
+
+        let data = Arc::new(data);
+        let faces = (0..data.faces.len())
+            .map(|index| Arc::new(Face::new(data.clone(), index)) as Arc<dyn Hit>)
+            .collect();
+
+        Ok(Mesh {
+            faces: NodeBuilder::new(faces)
+                .with_max_leaf_size(MAX_FACES_PER_LEAF)
+                .build_flat(),
+            data,
+        })
+    }
+}
+
+/// An indexed triangle mesh with its own bounding volume hierarchy over its faces.
+pub struct Mesh {
+    pub data: Arc<MeshData>,
+    faces: FlatBvh,
+}
+
+impl Hit for Mesh {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        self.faces.hit(ray, t_interval)
+    }
+
+    fn occluded(&self, ray: &Ray, t_interval: Interval) -> bool {
+        self.faces.occluded(ray, t_interval)
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        self.faces.bounding_box()
+    }
+}
+
+/// A single triangle of a mesh.
+struct Face {
+    mesh: Arc<MeshData>,
+    index: usize,
+    bounding_box: Aabb,
+}
+
+impl Face {
+    fn new(mesh: Arc<MeshData>, index: usize) -> Self {
+        let bounding_box =
+            triangle::bounding_box(&Self::attributes(&mesh.positions, mesh.faces[index]));
+
+        Self {
+            mesh,
+            index,
+            bounding_box,
+        }
+    }
+
+    fn attributes<T: Copy>(attributes: &[T], indices: [usize; 3]) -> [T; 3] {
+        indices.map(|index| attributes[index])
+    }
+}
+
+impl Hit for Face {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        let indices = self.mesh.faces[self.index];
+        let vertices = Self::attributes(&self.mesh.positions, indices);
+        let (t, barycentric) = triangle::intersect(ray, &vertices, &t_interval)?;
+
+        Some(triangle::hit_result(
+            ray,
+            t,
+            barycentric,
+            &vertices,
+            self.mesh
+                .normals
+                .as_ref()
+                .map(|normals| Self::attributes(normals, indices))
+                .as_ref(),
+            self.mesh
+                .uvs
+                .as_ref()
+                .map(|uvs| Self::attributes(uvs, indices))
+                .as_ref(),
+            self.mesh.material.clone(),
+        ))
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::Hit;
+    use crate::material::lambertian::Lambertian;
+    use crate::math::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::{MeshBuilder, MeshError};
+
+    fn square() -> MeshBuilder {
+        MeshBuilder::new(
+            vec![
+                Vec3(0.0, 0.0, -1.0),
+                Vec3(1.0, 0.0, -1.0),
+                Vec3(1.0, 1.0, -1.0),
+                Vec3(0.0, 1.0, -1.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+            Arc::new(Lambertian::colored(Color::white())),
+        )
+    }
+
+    #[test]
+    fn hit() {
+        let mesh = square()
+            .with_uvs(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+            .build()
+            .expect("mesh should be valid");
+
+        for (x, y) in [(0.75, 0.25), (0.25, 0.75)] {
+            let hit = mesh
+                .hit(
+                    &Ray {
+                        origin: Vec3(x, y, 0.0),
+                        direction: Vec3(0.0, 0.0, -1.0),
+                        time: 0.0,
+                    },
+                    Interval(0.0..f64::INFINITY),
+                )
+                .expect("ray should hit the mesh");
+
+            assert_abs_diff_eq!(hit.t, 1.0);
+            assert_abs_diff_eq!(hit.u, x);
+            assert_abs_diff_eq!(hit.v, y);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            square().with_normals(vec![Vec3::up()]).build().err(),
+            Some(MeshError::AttributeCountMismatch {
+                attribute: "normals"
+            })
+        );
+        assert_eq!(
+            MeshBuilder::new(
+                vec![Vec3::zero()],
+                vec![[0, 0, 1]],
+                Arc::new(Lambertian::colored(Color::white())),
+            )
+            .build()
+            .err(),
+            Some(MeshError::IndexOutOfBounds { face: 0, index: 1 })
+        );
+    }
+}
@@ -3,7 +3,7 @@ use std::sync::Arc;
 
 use approx::abs_diff_eq;
 
-use crate::hit::{Hit, HitResult};
+use crate::hit::{Hit, HitResult, SurfaceSample, BIAS};
 use crate::material::Material;
 use crate::math::aabb::Aabb;
 use crate::math::interval::Interval;
@@ -17,6 +17,7 @@ pub struct Quad {
     pub material: Arc<dyn Material>,
     bounding_box: Aabb,
     normal: Vec3,
+    area: f64,
     d: f64,  // plane coefficient
     w: Vec3, // basis frame helper vector
 }
@@ -25,6 +26,7 @@ impl Quad {
     pub fn new(origin: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
         let mut normal = u.cross(&v);
         let w = normal / normal.dot(&normal);
+        let area = normal.len();
         normal.normalize();
 
         Self {
@@ -34,10 +36,21 @@ impl Quad {
             material,
             bounding_box: Aabb::with_extrema(origin, origin + u + v).padded(0.0001),
             normal,
+            area,
             d: normal.dot(&origin),
             w,
         }
     }
+
+    /// Convert the uniform density over the area of the quad to solid angle as seen from
+    /// `origin`.
+    fn solid_angle_pdf(&self, origin: &Vec3, point: &Vec3) -> Option<f64> {
+        let to_point = *point - *origin;
+        let distance_sq = to_point.len_sq();
+        let cosine = self.normal.dot(&to_point).abs() / distance_sq.sqrt();
+
+        (cosine > f64::EPSILON).then(|| distance_sq / (cosine * self.area))
+    }
 }
 
 impl Hit for Quad {
@@ -74,4 +87,19 @@ impl Hit for Quad {
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+
+    fn sample(&self, origin: &Vec3, _time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        let point = self.origin + u.0 * self.u + u.1 * self.v;
+
+        Some(SurfaceSample {
+            point,
+            pdf: self.solid_angle_pdf(origin, &point)?,
+        })
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        self.hit(ray, Interval(BIAS..f64::INFINITY))
+            .and_then(|hit| self.solid_angle_pdf(&ray.origin, &hit.point))
+            .unwrap_or(0.0)
+    }
 }
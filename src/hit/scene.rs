@@ -11,8 +11,8 @@ pub struct Scene {
     bounding_box: Aabb,
 }
 
-#[allow(unused)]
 impl Scene {
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, object: Arc<dyn Hit>) -> Self {
         self.bounding_box.combine(object.bounding_box());
         self.objects.push(object);
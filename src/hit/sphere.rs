@@ -2,10 +2,11 @@ use std::f64::consts::PI;
 use std::ops::RangeBounds;
 use std::sync::Arc;
 
-use crate::hit::{Hit, HitResult};
+use crate::hit::{Hit, HitResult, SurfaceSample, BIAS};
 use crate::material::Material;
 use crate::math::aabb::Aabb;
 use crate::math::interval::Interval;
+use crate::math::onb::Onb;
 use crate::ray::Ray;
 use crate::vec::Vec3;
 
@@ -16,7 +17,6 @@ pub struct SphereBuilder {
     pub material: Arc<dyn Material>,
 }
 
-#[allow(dead_code)]
 impl SphereBuilder {
     pub fn new(center: Vec3, radius: f64, material: Arc<dyn Material>) -> Self {
         Self {
@@ -69,6 +69,20 @@ impl Sphere {
             .map_or(self.center, |movement| self.center + time * movement)
     }
 
+    /// The cosine of the half-angle of the cone the sphere covers as seen from `origin`, or `None`
+    /// if `origin` is inside of the sphere.
+    fn cos_theta_max(&self, origin: &Vec3, time: f64) -> Option<f64> {
+        let distance_sq = (self.center_at_time(time) - *origin).len_sq();
+        let radius_sq = self.radius * self.radius;
+
+        (distance_sq > radius_sq).then(|| (1.0 - radius_sq / distance_sq).sqrt())
+    }
+
+    /// The density of sampling directions uniformly within a cone.
+    fn cone_pdf(cos_theta_max: f64) -> f64 {
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
     fn uv(point: &Vec3) -> (f64, f64) {
         let theta = (-point.1).acos();
         let phi = (-point.2).atan2(point.0) + PI;
@@ -117,4 +131,42 @@ impl Hit for Sphere {
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+
+    /// Sample a direction within the cone the sphere covers as seen from `origin`.
+    fn sample(&self, origin: &Vec3, time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        let cos_theta_max = self.cos_theta_max(origin, time)?;
+        let to_center = self.center_at_time(time) - *origin;
+        let distance = to_center.len();
+
+        let cos_theta = u.1.mul_add(cos_theta_max - 1.0, 1.0);
+        let sin_theta_sq = cos_theta.mul_add(-cos_theta, 1.0).max(0.0);
+        let phi = 2.0 * PI * u.0;
+        let direction = Onb::new(&to_center).local(&Vec3(
+            phi.cos() * sin_theta_sq.sqrt(),
+            phi.sin() * sin_theta_sq.sqrt(),
+            cos_theta,
+        ));
+        // distance to the near side of the sphere along the sampled direction
+        let t = distance.mul_add(
+            cos_theta,
+            -(distance * distance)
+                .mul_add(-sin_theta_sq, self.radius * self.radius)
+                .max(0.0)
+                .sqrt(),
+        );
+
+        Some(SurfaceSample {
+            point: *origin + t * direction,
+            pdf: Self::cone_pdf(cos_theta_max),
+        })
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        if self.hit(ray, Interval(BIAS..f64::INFINITY)).is_none() {
+            return 0.0;
+        }
+
+        self.cos_theta_max(&ray.origin, ray.time)
+            .map_or(0.0, Self::cone_pdf)
+    }
 }
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::hit::{Hit, HitResult};
+use crate::hit::{Hit, HitResult, SurfaceSample};
 use crate::math::aabb::Aabb;
 use crate::math::interval::Interval;
 use crate::ray::Ray;
@@ -45,6 +45,23 @@ impl Hit for Translation {
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+
+    fn sample(&self, origin: &Vec3, time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        let sample = self.object.sample(&(*origin - self.offset), time, u)?;
+
+        Some(SurfaceSample {
+            point: sample.point + self.offset,
+            ..sample
+        })
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        self.object.pdf(&Ray {
+            origin: ray.origin - self.offset,
+            direction: ray.direction,
+            time: ray.time,
+        })
+    }
 }
 
 pub struct RotationY {
@@ -100,42 +117,36 @@ impl RotationY {
             bounding_box: Aabb::with_extrema(min, max),
         }
     }
+
+    fn to_object_space(&self, v: &Vec3) -> Vec3 {
+        Vec3(
+            self.cos_angle.mul_add(v.0, -(self.sin_angle * v.2)),
+            v.1,
+            self.sin_angle.mul_add(v.0, self.cos_angle * v.2),
+        )
+    }
+
+    fn to_world_space(&self, v: &Vec3) -> Vec3 {
+        Vec3(
+            self.cos_angle.mul_add(v.0, self.sin_angle * v.2),
+            v.1,
+            (-self.sin_angle).mul_add(v.0, self.cos_angle * v.2),
+        )
+    }
 }
 
 impl Hit for RotationY {
     fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
         let ray_object_space = Ray {
-            origin: Vec3(
-                self.cos_angle
-                    .mul_add(ray.origin.0, -(self.sin_angle * ray.origin.2)),
-                ray.origin.1,
-                self.sin_angle
-                    .mul_add(ray.origin.0, self.cos_angle * ray.origin.2),
-            ),
-            direction: Vec3(
-                self.cos_angle
-                    .mul_add(ray.direction.0, -(self.sin_angle * ray.direction.2)),
-                ray.direction.1,
-                self.sin_angle
-                    .mul_add(ray.direction.0, self.cos_angle * ray.direction.2),
-            ),
+            origin: self.to_object_space(&ray.origin),
+            direction: self.to_object_space(&ray.direction),
             time: ray.time,
-        }; // ray into object space
+        };
         let hit = self.object.hit(&ray_object_space, t_interval);
 
         if let Some(mut hit) = hit {
-            hit.point = Vec3(
-                self.cos_angle
-                    .mul_add(hit.point.0, self.sin_angle * hit.point.2),
-                hit.point.1,
-                (-self.sin_angle).mul_add(hit.point.0, self.cos_angle * hit.point.2),
-            ); // intersection point into world space
-            hit.normal = Vec3(
-                self.cos_angle
-                    .mul_add(hit.normal.0, self.sin_angle * hit.normal.2),
-                hit.normal.1,
-                (-self.sin_angle).mul_add(hit.normal.0, self.cos_angle * hit.normal.2),
-            ); // normal into world space
+            hit.point = self.to_world_space(&hit.point);
+            hit.normal = self.to_world_space(&hit.normal);
 
             Some(hit)
         } else {
@@ -146,4 +157,21 @@ impl Hit for RotationY {
     fn bounding_box(&self) -> &Aabb {
         &self.bounding_box
     }
+
+    fn sample(&self, origin: &Vec3, time: f64, u: (f64, f64)) -> Option<SurfaceSample> {
+        let sample = self.object.sample(&self.to_object_space(origin), time, u)?;
+
+        Some(SurfaceSample {
+            point: self.to_world_space(&sample.point),
+            ..sample
+        })
+    }
+
+    fn pdf(&self, ray: &Ray) -> f64 {
+        self.object.pdf(&Ray {
+            origin: self.to_object_space(&ray.origin),
+            direction: self.to_object_space(&ray.direction),
+            time: ray.time,
+        })
+    }
 }
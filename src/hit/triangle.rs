@@ -0,0 +1,221 @@
+use std::ops::RangeBounds;
+use std::sync::Arc;
+
+use crate::hit::{Hit, HitResult};
+use crate::material::Material;
+use crate::math::aabb::Aabb;
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::vec::Vec3;
+
+/// Determinants smaller than this are treated as rays parallel to the triangle.
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: Option<[(f64, f64); 3]>,
+    pub material: Arc<dyn Material>,
+    bounding_box: Aabb,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            normals: None,
+            uvs: None,
+            material,
+            bounding_box: bounding_box(&[a, b, c]),
+        }
+    }
+
+    /// Use per-vertex normals for smooth shading instead of the geometric normal.
+    pub const fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    /// Use per-vertex texture coordinates instead of the barycentric coordinates.
+    pub const fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+}
+
+impl Hit for Triangle {
+    fn hit(&self, ray: &Ray, t_interval: Interval) -> Option<HitResult> {
+        let (t, barycentric) = intersect(ray, &self.vertices, &t_interval)?;
+
+        Some(hit_result(
+            ray,
+            t,
+            barycentric,
+            &self.vertices,
+            self.normals.as_ref(),
+            self.uvs.as_ref(),
+            self.material.clone(),
+        ))
+    }
+
+    fn bounding_box(&self) -> &Aabb {
+        &self.bounding_box
+    }
+}
+
+pub(crate) fn bounding_box(vertices: &[Vec3; 3]) -> Aabb {
+    Aabb::with_extrema(vertices[0], vertices[1])
+        .combined(&Aabb::with_extrema(vertices[2], vertices[2]))
+        .padded(0.0001)
+}
+
+/// Intersect a ray with a triangle using the Möller-Trumbore algorithm.
+///
+/// Returns the ray parameter of the intersection and the barycentric coordinates of the second
+/// and third vertex.
+pub(crate) fn intersect(
+    ray: &Ray,
+    vertices: &[Vec3; 3],
+    t_interval: &Interval,
+) -> Option<(f64, (f64, f64))> {
+    let edges = (vertices[1] - vertices[0], vertices[2] - vertices[0]);
+    let p = ray.direction.cross(&edges.1);
+    let determinant = edges.0.dot(&p);
+    if determinant.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+
+    let determinant_inv = 1.0 / determinant;
+    let s = ray.origin - vertices[0];
+    let b1 = s.dot(&p) * determinant_inv;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q = s.cross(&edges.0);
+    let b2 = ray.direction.dot(&q) * determinant_inv;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edges.1.dot(&q) * determinant_inv;
+    if !t_interval.contains(&t) {
+        return None;
+    }
+
+    Some((t, (b1, b2)))
+}
+
+/// Build the hit result for an intersection found by [`intersect`], interpolating the vertex
+/// attributes if there are any.
+pub(crate) fn hit_result(
+    ray: &Ray,
+    t: f64,
+    (b1, b2): (f64, f64),
+    vertices: &[Vec3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: Option<&[(f64, f64); 3]>,
+    material: Arc<dyn Material>,
+) -> HitResult {
+    let b0 = 1.0 - b1 - b2;
+    let geometric_normal = (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .normalized();
+    let (u, v) = uvs.map_or((b1, b2), |uvs| {
+        (
+            b0.mul_add(uvs[0].0, b1.mul_add(uvs[1].0, b2 * uvs[2].0)),
+            b0.mul_add(uvs[0].1, b1.mul_add(uvs[1].1, b2 * uvs[2].1)),
+        )
+    });
+    let mut hit = HitResult::new(ray, t, u, v, ray.at(t), geometric_normal, material);
+
+    if let Some(normals) = normals {
+        let shading_normal = (b0 * normals[0] + b1 * normals[1] + b2 * normals[2]).normalized();
+
+        // keep the shading normal on the side of the surface the ray came from
+        hit.normal = if shading_normal.dot(&hit.normal) < 0.0 {
+            -shading_normal
+        } else {
+            shading_normal
+        };
+    }
+
+    hit
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::Hit;
+    use crate::material::lambertian::Lambertian;
+    use crate::math::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::Triangle;
+
+    fn triangle() -> Triangle {
+        Triangle::new(
+            Vec3(0.0, 0.0, -1.0),
+            Vec3(1.0, 0.0, -1.0),
+            Vec3(0.0, 1.0, -1.0),
+            Arc::new(Lambertian::colored(Color::white())),
+        )
+    }
+
+    fn ray_to(x: f64, y: f64) -> Ray {
+        Ray {
+            origin: Vec3(x, y, 0.0),
+            direction: Vec3(0.0, 0.0, -1.0),
+            time: 0.0,
+        }
+    }
+
+    #[test]
+    fn hit() {
+        let hit = triangle()
+            .hit(&ray_to(0.25, 0.5), Interval(0.0..f64::INFINITY))
+            .expect("ray should hit the triangle");
+
+        assert_abs_diff_eq!(hit.t, 1.0);
+        assert_abs_diff_eq!(hit.point, Vec3(0.25, 0.5, -1.0));
+        assert_abs_diff_eq!(hit.normal, Vec3(0.0, 0.0, 1.0));
+        assert!(hit.front_face);
+        assert_abs_diff_eq!(hit.u, 0.25);
+        assert_abs_diff_eq!(hit.v, 0.5);
+    }
+
+    #[test]
+    fn miss() {
+        let triangle = triangle();
+
+        assert!(triangle
+            .hit(&ray_to(0.6, 0.6), Interval(0.0..f64::INFINITY))
+            .is_none());
+        assert!(triangle
+            .hit(&ray_to(0.25, 0.5), Interval(0.0..0.5))
+            .is_none());
+    }
+
+    #[test]
+    fn interpolated_attributes() {
+        let triangle = triangle()
+            .with_normals([
+                Vec3(0.0, 0.0, 1.0),
+                Vec3(1.0, 0.0, 0.0),
+                Vec3(0.0, 0.0, 1.0),
+            ])
+            .with_uvs([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+        let hit = triangle
+            .hit(&ray_to(0.5, 0.0), Interval(0.0..f64::INFINITY))
+            .expect("ray should hit the triangle");
+
+        assert_abs_diff_eq!(hit.normal, Vec3(1.0, 0.0, 1.0).normalized());
+        assert_abs_diff_eq!(hit.u, 0.5);
+        assert_abs_diff_eq!(hit.v, 0.0);
+    }
+}
@@ -1,21 +1,29 @@
+//! Images to render into, to load textures from and to write to disk.
+
 use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
 use std::fs::{self, create_dir_all, File};
-use std::io::{BufReader, Write};
+use std::io::{BufReader, BufWriter, Write};
+use std::iter;
 use std::path::Path;
 
+#[cfg(feature = "preview")]
 use egui::ColorImage;
-use image::codecs::hdr::HdrDecoder;
-use image::{ImageBuffer, Rgb};
+use exr::prelude::{f16, AnyChannel, AnyChannels, FlatSamples, SmallVec, WritableImage};
+use image::codecs::hdr::{HdrDecoder, HdrEncoder};
+use image::{DynamicImage, ImageBuffer, Rgb};
 
 use crate::color::Color;
 use crate::math;
+use crate::tone_map::ToneMapper;
 
 #[derive(Debug)]
 pub enum ImageError {
-    #[allow(dead_code)] // can be removed as soon as this error is logged correctly
     IOError(std::io::Error),
-    #[allow(dead_code)] // can be removed as soon as this error is logged correctly
+    LoadError(image::ImageError),
     SaveError(image::ImageError),
+    ExrError(exr::error::Error),
+    InvalidPfm(&'static str),
     AveragingZeroImages,
     DimensionsMismatch,
 }
@@ -26,6 +34,30 @@ impl From<std::io::Error> for ImageError {
     }
 }
 
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IOError(error) => write!(f, "{error}"),
+            Self::LoadError(error) => write!(f, "could not load image: {error}"),
+            Self::SaveError(error) => write!(f, "could not save image: {error}"),
+            Self::ExrError(error) => write!(f, "could not save EXR image: {error}"),
+            Self::InvalidPfm(reason) => write!(f, "invalid PFM image: {reason}"),
+            Self::AveragingZeroImages => write!(f, "can't average zero images"),
+            Self::DimensionsMismatch => write!(f, "images have different dimensions"),
+        }
+    }
+}
+
+/// How precisely the channels of an EXR file are stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExrPrecision {
+    /// 16-bit floats, enough for colors and half the size.
+    Half,
+    /// 32-bit floats, for data that needs the precision, like depth.
+    Float,
+}
+
+/// A two-dimensional buffer of linear colors.
 #[derive(Debug, Clone)]
 pub struct Image {
     width: u32,
@@ -33,7 +65,6 @@ pub struct Image {
     data: Vec<Color>,
 }
 
-#[allow(dead_code)]
 impl Image {
     pub fn with_aspect_ratio(width: u32, aspect_ratio: f64, color: Color) -> Self {
         #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
@@ -53,17 +84,27 @@ impl Image {
         }
     }
 
-    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+    /// Load an image from disk. Images with more than 8 bits per channel, like Radiance HDR
+    /// (`.hdr`), EXR and portable float map (`.pfm`) files, are loaded without quantization.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image can't be opened or decoded.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        if has_extension(path.as_ref(), "pfm") {
+            return Self::load_pfm(path);
+        }
+
         let width: u32;
         let height: u32;
-        let data = if path.as_ref().extension() == Some(OsStr::new("hdr")) {
-            let file = File::open(&path).expect("Failed to open image");
-            let decoder = HdrDecoder::new(BufReader::new(file)).expect("Failed to read hdr image");
+        let data = if has_extension(path.as_ref(), "hdr") {
+            let file = File::open(&path)?;
+            let decoder = HdrDecoder::new(BufReader::new(file)).map_err(ImageError::LoadError)?;
             width = decoder.metadata().width;
             height = decoder.metadata().height;
             decoder
                 .read_image_hdr()
-                .expect("Failed to read image")
+                .map_err(ImageError::LoadError)?
                 .into_iter()
                 .map(|pixel| {
                     Color::new(
@@ -74,23 +115,121 @@ impl Image {
                 })
                 .collect()
         } else {
-            let image = image::open(path).expect("Failed to open image");
+            let image = image::open(path).map_err(ImageError::LoadError)?;
             width = image.width();
             height = image.height();
-            image
-                .into_rgb8()
-                .enumerate_pixels()
-                .map(|(_, _, pixel)| Color::from(*pixel))
-                .collect()
+            if matches!(
+                image,
+                DynamicImage::ImageLuma8(_)
+                    | DynamicImage::ImageLumaA8(_)
+                    | DynamicImage::ImageRgb8(_)
+                    | DynamicImage::ImageRgba8(_)
+            ) {
+                image
+                    .into_rgb8()
+                    .enumerate_pixels()
+                    .map(|(_, _, pixel)| Color::from(*pixel))
+                    .collect()
+            } else {
+                // 16-bit and floating point images, which may be brighter than white
+                image
+                    .into_rgb32f()
+                    .pixels()
+                    .map(|pixel| {
+                        Color::new(
+                            f64::from(pixel.0[0]),
+                            f64::from(pixel.0[1]),
+                            f64::from(pixel.0[2]),
+                        )
+                    })
+                    .collect()
+            }
         };
 
-        Self {
+        Ok(Self {
             width,
             height,
             data,
+        })
+    }
+
+    /// Load a portable float map, in color (`PF`) or gray scale (`Pf`).
+    fn load_pfm<P: AsRef<Path>>(path: P) -> Result<Self, ImageError> {
+        let bytes = fs::read(path)?;
+        let mut position = 0;
+        let mut header = || pfm_header_token(&bytes, &mut position);
+
+        let channels = match header() {
+            Some("PF") => 3,
+            Some("Pf") => 1,
+            _ => return Err(ImageError::InvalidPfm("unknown format")),
+        };
+        let (Some(width), Some(height)) = (
+            header().and_then(|token| token.parse::<u32>().ok()),
+            header().and_then(|token| token.parse::<u32>().ok()),
+        ) else {
+            return Err(ImageError::InvalidPfm("invalid dimensions"));
+        };
+        if width == 0 || height == 0 {
+            return Err(ImageError::InvalidPfm("invalid dimensions"));
         }
+        // the sign of the scale is the byte order, its magnitude is not used by anyone
+        let Some(scale) = header().and_then(|token| token.parse::<f32>().ok()) else {
+            return Err(ImageError::InvalidPfm("invalid scale"));
+        };
+        // exactly one white space character separates the header from the data
+        let data = bytes
+            .get(position + 1..)
+            .ok_or(ImageError::InvalidPfm("missing data"))?;
+
+        // the dimensions come from the file, so a huge image must not overflow the size
+        let value_count = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(channels))
+            .ok_or(ImageError::InvalidPfm("invalid dimensions"))?;
+        let byte_count = value_count
+            .checked_mul(4)
+            .ok_or(ImageError::InvalidPfm("invalid dimensions"))?;
+        if data.len() < byte_count {
+            return Err(ImageError::InvalidPfm("not enough data"));
+        }
+        let values = data
+            .chunks_exact(4)
+            .take(value_count)
+            .map(|bytes| {
+                let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+                f64::from(if scale < 0.0 {
+                    f32::from_le_bytes(bytes)
+                } else {
+                    f32::from_be_bytes(bytes)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // the rows are stored from the bottom to the top
+        let data = values
+            .chunks_exact(width as usize * channels)
+            .rev()
+            .flat_map(|row| row.chunks_exact(channels))
+            .map(|pixel| match *pixel {
+                [r, g, b] => Color::new(r, g, b),
+                [value] => Color::new(value, value, value),
+                _ => unreachable!("pixels have one or three channels"),
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
     }
 
+    /// Average multiple images of the same size pixel by pixel.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `images` is empty or the images don't all have the same size.
     pub fn average(images: &Vec<Self>) -> Result<Self, ImageError> {
         if images.is_empty() {
             return Err(ImageError::AveragingZeroImages);
@@ -125,7 +264,7 @@ impl Image {
         self.height
     }
 
-    pub fn pixel_count(&self) -> usize {
+    pub const fn pixel_count(&self) -> usize {
         self.data.len()
     }
 
@@ -145,19 +284,37 @@ impl Image {
         self.data[(y * self.width + x) as usize]
     }
 
+    /// A copy of the image with `map` applied to every pixel.
+    #[must_use]
+    pub fn map(&self, map: impl Fn(Color) -> Color) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            data: self.data.iter().copied().map(map).collect(),
+        }
+    }
+
+    /// A copy of the image with every pixel mapped into the displayable range by `tone_mapper`.
+    #[must_use]
+    pub fn tone_mapped(&self, tone_mapper: &ToneMapper) -> Self {
+        self.map(|color| tone_mapper.apply(color))
+    }
+
     pub fn get_pixel_by_uv(&self, u: f64, v: f64) -> Color {
         #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
         self.get_pixel(
             (math::clamp_repeating(u) * f64::from(self.width)) as u32,
-            ((1.0 - math::clamp_repeating(v)) * f64::from(self.height)) as u32,
+            (((1.0 - math::clamp_repeating(v)) * f64::from(self.height)) as u32)
+                .min(self.height - 1),
         )
     }
 
-    pub fn write_ppm<P: AsRef<Path>>(
-        &self,
-        path: P,
-        to_gamma_space: bool,
-    ) -> Result<(), ImageError> {
+    /// Write the image as a plain text PPM file, creating the parent directories if necessary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn write_ppm<P: AsRef<Path>>(&self, path: P, srgb: bool) -> Result<(), ImageError> {
         // create the directory and the file if they don't exist
         if let Some(directory) = path.as_ref().parent() {
             create_dir_all(directory)?;
@@ -173,8 +330,8 @@ impl Image {
             for x in 0..self.width {
                 let pixel = format!(
                     "{}\n",
-                    if to_gamma_space {
-                        self.get_pixel(x, y).to_gamma_space()
+                    if srgb {
+                        self.get_pixel(x, y).encode_srgb()
                     } else {
                         self.get_pixel(x, y)
                     }
@@ -187,15 +344,16 @@ impl Image {
         Ok(())
     }
 
-    pub fn write_png<P: AsRef<Path>>(
-        &self,
-        path: P,
-        to_gamma_space: bool,
-    ) -> Result<(), ImageError> {
+    /// Write the image as a PNG file, creating the parent directories if necessary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn write_png<P: AsRef<Path>>(&self, path: P, srgb: bool) -> Result<(), ImageError> {
         let image: ImageBuffer<Rgb<u8>, _> =
             ImageBuffer::from_fn(self.width, self.height, |x, y| {
-                if to_gamma_space {
-                    self.get_pixel(x, y).to_gamma_space().into()
+                if srgb {
+                    self.get_pixel(x, y).encode_srgb().into()
                 } else {
                     self.get_pixel(x, y).into()
                 }
@@ -204,8 +362,158 @@ impl Image {
         path.as_ref().parent().map(fs::create_dir_all);
         image.save(path).map_err(ImageError::SaveError)
     }
+
+    /// Write the image as a Radiance HDR file, creating the parent directories if necessary.
+    ///
+    /// The colors are stored linearly and without clamping, with a shared exponent for the three
+    /// channels of a pixel, which keeps about 1 % of precision.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn write_hdr<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        if let Some(directory) = path.as_ref().parent() {
+            create_dir_all(directory)?;
+        }
+        let file = BufWriter::new(File::create(path)?);
+        #[allow(clippy::cast_possible_truncation)]
+        let data = self
+            .data
+            .iter()
+            .map(|color| Rgb([color.r() as f32, color.g() as f32, color.b() as f32]))
+            .collect::<Vec<_>>();
+
+        HdrEncoder::new(file)
+            .encode(&data, self.width as usize, self.height as usize)
+            .map_err(ImageError::SaveError)
+    }
+
+    /// Write the image as a portable float map with 32-bit floats, creating the parent
+    /// directories if necessary.
+    ///
+    /// The colors are stored linearly and without clamping.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn write_pfm<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        if let Some(directory) = path.as_ref().parent() {
+            create_dir_all(directory)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+
+        // a negative scale marks the data as little endian
+        file.write_all(format!("PF\n{} {}\n-1.0\n", self.width, self.height).as_bytes())?;
+        // the rows are stored from the bottom to the top
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let pixel = self.get_pixel(x, y);
+                for value in [pixel.r(), pixel.g(), pixel.b()] {
+                    #[allow(clippy::cast_possible_truncation)]
+                    file.write_all(&(value as f32).to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(file.flush()?)
+    }
+
+    /// Write the image as an EXR file, creating the parent directories if necessary.
+    ///
+    /// Unlike the other formats, the colors are written as they are: linear and without
+    /// clamping, so nothing above 1 is lost. The image is stored in the `R`, `G` and `B`
+    /// channels, and every one of `layers` in the channels `<name>.R`, `<name>.G` and `<name>.B`,
+    /// which compositing software shows as separate layers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a layer has a different size than the image, or if the file can't be
+    /// written.
+    pub fn write_exr<P: AsRef<Path>>(
+        &self,
+        path: P,
+        precision: ExrPrecision,
+        layers: &[(&str, &Self)],
+    ) -> Result<(), ImageError> {
+        if layers
+            .iter()
+            .any(|(_, layer)| layer.resolution() != self.resolution())
+        {
+            return Err(ImageError::DimensionsMismatch);
+        }
+
+        let mut channels = SmallVec::new();
+        for (prefix, image) in iter::once(("", self)).chain(layers.iter().copied()) {
+            for (channel, value) in [
+                ("R", Color::r as fn(&Color) -> f64),
+                ("G", Color::g),
+                ("B", Color::b),
+            ] {
+                let name = if prefix.is_empty() {
+                    channel.to_string()
+                } else {
+                    format!("{prefix}.{channel}")
+                };
+                channels.push(AnyChannel::new(
+                    name.as_str(),
+                    image.exr_samples(precision, value),
+                ));
+            }
+        }
+
+        path.as_ref().parent().map(fs::create_dir_all);
+        exr::image::Image::from_channels(
+            (self.width as usize, self.height as usize),
+            AnyChannels::sort(channels),
+        )
+        .write()
+        .to_file(path)
+        .map_err(ImageError::ExrError)
+    }
+
+    /// One channel of the image, picked from every pixel by `value`, row by row.
+    #[allow(clippy::cast_possible_truncation)]
+    fn exr_samples(&self, precision: ExrPrecision, value: fn(&Color) -> f64) -> FlatSamples {
+        match precision {
+            ExrPrecision::Half => {
+                FlatSamples::F16(self.data.iter().map(|c| f16::from_f64(value(c))).collect())
+            }
+            ExrPrecision::Float => {
+                FlatSamples::F32(self.data.iter().map(|c| value(c) as f32).collect())
+            }
+        }
+    }
+}
+
+/// Whether `path` has the file extension `extension`, ignoring case.
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|actual| actual.eq_ignore_ascii_case(extension))
+}
+
+/// The next white space separated token of the header of a portable float map at `position`,
+/// leaving `position` right after it.
+fn pfm_header_token<'a>(bytes: &'a [u8], position: &mut usize) -> Option<&'a str> {
+    let start = *position
+        + bytes
+            .get(*position..)?
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+    let length = bytes
+        .get(start..)?
+        .iter()
+        .take_while(|byte| !byte.is_ascii_whitespace())
+        .count();
+    *position = start + length;
+
+    std::str::from_utf8(&bytes[start..start + length])
+        .ok()
+        .filter(|token| !token.is_empty())
 }
 
+#[cfg(feature = "preview")]
 impl From<&Image> for ColorImage {
     fn from(value: &Image) -> Self {
         Self::from_rgb(
@@ -221,11 +529,13 @@ impl From<&Image> for ColorImage {
 
 #[cfg(test)]
 mod tests {
+    use std::{env, fs, process};
+
     use approx::assert_abs_diff_eq;
 
     use crate::color::Color;
 
-    use super::Image;
+    use super::{ExrPrecision, Image, ImageError};
 
     #[test]
     fn create_with_aspect_ratio() {
@@ -255,4 +565,167 @@ mod tests {
         image.set_pixel(1, 1, color);
         assert_abs_diff_eq!(image.get_pixel(1, 1), color);
     }
+
+    #[test]
+    fn exr_keeps_highlights_and_layers() {
+        let path = env::temp_dir().join(format!("raytracer-{}-layers.exr", process::id()));
+        let mut image = Image::with_dimensions(3, 2, Color::black());
+        image.set_pixel(2, 1, Color::new(12.5, 0.25, 3.0));
+        let albedo = Image::with_dimensions(3, 2, Color::new(0.5, 0.5, 0.5));
+
+        image
+            .write_exr(&path, ExrPrecision::Float, &[("albedo", &albedo)])
+            .expect("could not write image");
+        let file = exr::prelude::read_all_flat_layers_from_file(&path).expect("could not read");
+        fs::remove_file(&path).expect("could not remove image");
+
+        let channels = &file.layer_data[0].channel_data.list;
+        let names = channels
+            .iter()
+            .map(|channel| channel.name.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["B", "G", "R", "albedo.B", "albedo.G", "albedo.R"]);
+        let red = channels[2].sample_data.values_as_f32().collect::<Vec<_>>();
+        assert_abs_diff_eq!(red[5], 12.5);
+        let albedo_red = channels[5].sample_data.values_as_f32().collect::<Vec<_>>();
+        assert_abs_diff_eq!(albedo_red[0], 0.5);
+
+        let small = Image::with_dimensions(1, 1, Color::black());
+        assert!(matches!(
+            image.write_exr(&path, ExrPrecision::Half, &[("small", &small)]),
+            Err(ImageError::DimensionsMismatch)
+        ));
+    }
+
+    #[test]
+    fn load_float_images() {
+        let mut image = Image::with_dimensions(3, 2, Color::new(0.25, 0.5, 0.75));
+        image.set_pixel(2, 1, Color::new(12.5, 0.25, 3.0));
+
+        // upper case extensions are recognized as well
+        for extension in ["exr", "PFM", "HDR"] {
+            let path =
+                env::temp_dir().join(format!("raytracer-{}-float.{extension}", process::id()));
+            match extension {
+                "exr" => image.write_exr(&path, ExrPrecision::Float, &[]),
+                "PFM" => image.write_pfm(&path),
+                _ => image.write_hdr(&path),
+            }
+            .expect("could not write image");
+            let loaded = Image::load(&path);
+            fs::remove_file(&path).expect("could not remove image");
+
+            let loaded = loaded.expect("could not load image");
+            assert_abs_diff_eq!(
+                loaded.get_pixel(2, 1),
+                Color::new(12.5, 0.25, 3.0),
+                epsilon = 0.1
+            );
+            assert_abs_diff_eq!(
+                loaded.get_pixel(0, 0),
+                Color::new(0.25, 0.5, 0.75),
+                epsilon = 0.01
+            );
+        }
+    }
+
+    /// An image with a gradient and a highlight far brighter than white.
+    fn high_dynamic_range() -> Image {
+        let mut image = Image::with_dimensions(12, 3, Color::black());
+        for y in 0..3 {
+            for x in 0..12 {
+                let value = f64::from(x) / 11.0;
+                image.set_pixel(x, y, Color::new(value, 0.5 * value, f64::from(y)));
+            }
+        }
+        image.set_pixel(4, 1, Color::new(250.0, 80.0, 1.5));
+
+        image
+    }
+
+    #[test]
+    fn hdr_round_trip() {
+        let path = env::temp_dir().join(format!("raytracer-{}-round-trip.hdr", process::id()));
+        let image = high_dynamic_range();
+
+        image.write_hdr(&path).expect("could not write image");
+        let loaded = Image::load(&path).expect("could not load image");
+        fs::remove_file(&path).expect("could not remove image");
+
+        assert_eq!(loaded.resolution(), image.resolution());
+        for y in 0..3 {
+            for x in 0..12 {
+                let (expected, actual) = (image.get_pixel(x, y), loaded.get_pixel(x, y));
+                // the shared exponent keeps 8 bits for the brightest channel
+                let epsilon = expected.max_component() / 128.0;
+                assert_abs_diff_eq!(actual, expected, epsilon = epsilon);
+            }
+        }
+    }
+
+    #[test]
+    fn pfm_round_trip() {
+        let path = env::temp_dir().join(format!("raytracer-{}-round-trip.pfm", process::id()));
+        let image = high_dynamic_range();
+
+        image.write_pfm(&path).expect("could not write image");
+        let loaded = Image::load(&path).expect("could not load image");
+        fs::remove_file(&path).expect("could not remove image");
+
+        assert_eq!(loaded.resolution(), image.resolution());
+        for y in 0..3 {
+            for x in 0..12 {
+                assert_abs_diff_eq!(
+                    loaded.get_pixel(x, y),
+                    image.get_pixel(x, y),
+                    epsilon = 1e-6
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn load_gray_big_endian_pfm() {
+        let path = env::temp_dir().join(format!("raytracer-{}-gray.pfm", process::id()));
+        let mut bytes = b"Pf\n2 2\n1.0\n".to_vec();
+        for value in [1.0f32, 2.0, 3.0, 4.0] {
+            bytes.extend(value.to_be_bytes());
+        }
+
+        fs::write(&path, &bytes).expect("could not write image");
+        let loaded = Image::load(&path);
+        fs::write(&path, &bytes[..bytes.len() - 1]).expect("could not write image");
+        let truncated = Image::load(&path);
+        fs::remove_file(&path).expect("could not remove image");
+
+        // the last row in the file is the top one
+        let loaded = loaded.expect("could not load image");
+        assert_abs_diff_eq!(loaded.get_pixel(0, 0), Color::new(3.0, 3.0, 3.0));
+        assert_abs_diff_eq!(loaded.get_pixel(1, 1), Color::new(2.0, 2.0, 2.0));
+        assert!(matches!(truncated, Err(ImageError::InvalidPfm(_))));
+    }
+
+    #[test]
+    fn reject_invalid_pfm_dimensions() {
+        let path = env::temp_dir().join(format!("raytracer-{}-dimensions.pfm", process::id()));
+        let mut results = Vec::new();
+        for header in [
+            &b"PF\n0 5\n-1.0\n"[..],
+            b"PF\n5 0\n-1.0\n",
+            b"PF\n4294967295 4294967295\n-1.0\n",
+        ] {
+            let mut bytes = header.to_vec();
+            bytes.extend([0; 12]);
+            fs::write(&path, &bytes).expect("could not write image");
+            results.push(Image::load(&path));
+        }
+        fs::remove_file(&path).expect("could not remove image");
+
+        for result in results {
+            assert!(matches!(
+                result,
+                Err(ImageError::InvalidPfm("invalid dimensions"))
+            ));
+        }
+    }
 }
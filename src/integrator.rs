@@ -0,0 +1,87 @@
+//! Estimating the light arriving at the camera along a ray.
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hit::{Hit, HitResult};
+use crate::ray::Ray;
+use crate::sampler::SampleStream;
+
+pub mod brute_force;
+pub mod path;
+
+/// Something that happened while tracing a path through the scene.
+///
+/// `bounces` is the number of times the path was scattered before the event, so events at the
+/// surface the camera ray hits have 0 bounces.
+#[derive(Clone, Copy)]
+pub enum PathEvent<'a> {
+    /// The path hit a surface.
+    Hit {
+        bounces: u32,
+        ray: &'a Ray,
+        hit: &'a HitResult,
+    },
+    /// Light reached the camera along the path.
+    ///
+    /// `radiance` is already attenuated by everything it passed on its way to the camera.
+    Light {
+        bounces: u32,
+        source: LightSource,
+        radiance: Color,
+    },
+    /// The path won't be traced any further.
+    End { bounces: u32, reason: PathEnd },
+}
+
+/// Where the light of a [`PathEvent::Light`] was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightSource {
+    /// The path hit an emissive surface.
+    Emission,
+    /// A light was sampled directly from the surface the path hit.
+    Direct,
+    /// The path left the scene.
+    Background,
+}
+
+/// Why a path ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathEnd {
+    /// The path left the scene.
+    Escaped,
+    /// The surface absorbed all of the light.
+    Absorbed,
+    /// Russian roulette ended the path.
+    Roulette,
+    /// The path reached the camera's maximum number of bounces.
+    MaxBounces,
+}
+
+/// A strategy for estimating the light arriving along camera rays.
+pub trait Integrator: Send + Sync {
+    /// Estimate the light arriving along `ray` from the objects in `root`.
+    ///
+    /// The `camera` provides the rest of the scene and the limits for the paths, and `samples`
+    /// the numbers for every random decision along them. Every step of the path is reported to
+    /// `record` as it is traced.
+    fn radiance(
+        &self,
+        camera: &Camera,
+        root: &dyn Hit,
+        ray: Ray,
+        samples: &mut SampleStream,
+        record: &mut dyn FnMut(PathEvent),
+    ) -> Color;
+}
+
+/// The chance of a path continuing after `bounces` bounces, given the fraction of light that
+/// would reach the camera along it.
+///
+/// Paths are always continued until they reach the camera's roulette depth.
+const fn survival(camera: &Camera, bounces: u32, throughput: Color) -> f64 {
+    if bounces < camera.roulette_depth {
+        1.0
+    } else {
+        throughput.max_component().min(1.0)
+    }
+}
@@ -0,0 +1,102 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hit::{Hit, BIAS};
+use crate::integrator::{survival, Integrator, LightSource, PathEnd, PathEvent};
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::SampleStream;
+
+/// A path tracer that only finds light by following the scattered rays.
+///
+/// It ignores the lights of the camera, so it converges a lot slower than a
+/// [`PathTracer`](crate::integrator::path::PathTracer), but it is simple enough to serve as a
+/// reference.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BruteForce;
+
+impl Integrator for BruteForce {
+    fn radiance(
+        &self,
+        camera: &Camera,
+        root: &dyn Hit,
+        mut ray: Ray,
+        samples: &mut SampleStream,
+        record: &mut dyn FnMut(PathEvent),
+    ) -> Color {
+        let mut radiance = Color::black();
+        let mut throughput = Color::white();
+
+        for bounces in 0..camera.max_bounces {
+            samples.start_bounce(bounces);
+            let Some(hit) = root.hit(&ray, Interval(BIAS..f64::INFINITY)) else {
+                let light = throughput * camera.background.background(&ray);
+                record(PathEvent::Light {
+                    bounces,
+                    source: LightSource::Background,
+                    radiance: light,
+                });
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Escaped,
+                });
+
+                return radiance + light;
+            };
+            record(PathEvent::Hit {
+                bounces,
+                ray: &ray,
+                hit: &hit,
+            });
+
+            let emitted = hit.material.emitted(hit.u, hit.v, &hit.point);
+            if emitted != Color::black() {
+                let light = throughput * emitted;
+                record(PathEvent::Light {
+                    bounces,
+                    source: LightSource::Emission,
+                    radiance: light,
+                });
+                radiance += light;
+            }
+
+            let wo = -ray.direction.normalized();
+            let Some(scatter) = hit
+                .material
+                .sample(&hit, &wo, samples)
+                .filter(|scatter| scatter.specular || scatter.pdf > 0.0)
+            else {
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Absorbed,
+                });
+
+                return radiance;
+            };
+
+            throughput *= scatter.weight(&hit.normal);
+            let survival = survival(camera, bounces + 1, throughput);
+            if survival <= samples.roulette() {
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Roulette,
+                });
+
+                return radiance;
+            }
+            throughput /= survival;
+
+            ray = Ray {
+                origin: hit.point,
+                direction: scatter.direction,
+                time: ray.time,
+            };
+        }
+
+        record(PathEvent::End {
+            bounces: camera.max_bounces,
+            reason: PathEnd::MaxBounces,
+        });
+
+        radiance
+    }
+}
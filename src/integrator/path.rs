@@ -0,0 +1,444 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hit::{Hit, HitResult, BIAS};
+use crate::integrator::{survival, Integrator, LightSource, PathEnd, PathEvent};
+use crate::math::interval::Interval;
+use crate::ray::Ray;
+use crate::sampler::SampleStream;
+use crate::vec::Vec3;
+
+/// A path tracer that samples the lights of the camera directly at every surface that doesn't
+/// scatter into discrete directions.
+///
+/// Light found by sampling the lights and by following the scattered rays is weighted with the
+/// power heuristic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn radiance(
+        &self,
+        camera: &Camera,
+        root: &dyn Hit,
+        mut ray: Ray,
+        samples: &mut SampleStream,
+        record: &mut dyn FnMut(PathEvent),
+    ) -> Color {
+        let mut radiance = Color::black();
+        let mut throughput = Color::white();
+        // the density of scattering into the direction of `ray` if the lights were also sampled
+        // at the surface it leaves from
+        let mut scattering_pdf = None;
+
+        for bounces in 0..camera.max_bounces {
+            samples.start_bounce(bounces);
+            let Some(hit) = root.hit(&ray, Interval(BIAS..f64::INFINITY)) else {
+                let light = throughput * camera.background.background(&ray);
+                record(PathEvent::Light {
+                    bounces,
+                    source: LightSource::Background,
+                    radiance: light,
+                });
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Escaped,
+                });
+
+                return radiance + light;
+            };
+            record(PathEvent::Hit {
+                bounces,
+                ray: &ray,
+                hit: &hit,
+            });
+
+            let mut emitted = hit.material.emitted(hit.u, hit.v, &hit.point);
+            if let Some(scattering_pdf) = scattering_pdf {
+                if is_on_light(camera, &ray, hit.t) {
+                    emitted *= power_heuristic(scattering_pdf, camera.lights.pdf(&ray));
+                }
+            }
+            if emitted != Color::black() {
+                let light = throughput * emitted;
+                record(PathEvent::Light {
+                    bounces,
+                    source: LightSource::Emission,
+                    radiance: light,
+                });
+                radiance += light;
+            }
+
+            let wo = -ray.direction.normalized();
+            let Some(scatter) = hit
+                .material
+                .sample(&hit, &wo, samples)
+                .filter(|scatter| scatter.specular || scatter.pdf > 0.0)
+            else {
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Absorbed,
+                });
+
+                return radiance;
+            };
+
+            // the light found by sampling a light has to be able to reach the camera
+            scattering_pdf = Some(scatter.pdf).filter(|_| {
+                !scatter.specular && !camera.lights.is_empty() && bounces + 1 < camera.max_bounces
+            });
+            if scattering_pdf.is_some() {
+                let light =
+                    throughput * sample_lights(camera, root, &ray, &hit, &wo, samples.light());
+                if light != Color::black() {
+                    record(PathEvent::Light {
+                        bounces,
+                        source: LightSource::Direct,
+                        radiance: light,
+                    });
+                    radiance += light;
+                }
+            }
+
+            // end the path with a chance that grows as less light reaches the camera along it,
+            // and count the paths that continue for the ones that didn't
+            throughput *= scatter.weight(&hit.normal);
+            let survival = survival(camera, bounces + 1, throughput);
+            if survival <= samples.roulette() {
+                record(PathEvent::End {
+                    bounces,
+                    reason: PathEnd::Roulette,
+                });
+
+                return radiance;
+            }
+            throughput /= survival;
+
+            ray = Ray {
+                origin: hit.point,
+                direction: scatter.direction,
+                time: ray.time,
+            };
+        }
+
+        record(PathEvent::End {
+            bounces: camera.max_bounces,
+            reason: PathEnd::MaxBounces,
+        });
+
+        radiance
+    }
+}
+
+/// Estimate the light arriving directly from one of the lights of the camera and scattered
+/// towards `wo`, choosing the light and the point on it with `u`.
+fn sample_lights(
+    camera: &Camera,
+    root: &dyn Hit,
+    ray: &Ray,
+    hit: &HitResult,
+    wo: &Vec3,
+    u: (f64, f64),
+) -> Color {
+    let Some(sample) = camera.lights.sample(&hit.point, ray.time, u) else {
+        return Color::black();
+    };
+    let wi = (sample.point - hit.point).normalized();
+    let f = hit.material.eval(hit, wo, &wi);
+    if sample.pdf <= 0.0 || f == Color::black() {
+        return Color::black();
+    }
+
+    let shadow_ray = Ray {
+        origin: hit.point,
+        direction: wi,
+        time: ray.time,
+    };
+    // the closest light along the ray gives the emission, so only the objects in front of it
+    // have to be tested, which can stop at the first one they find. A light closer than twice
+    // the bias leaves no room for anything in between.
+    let Some(light) = camera
+        .lights
+        .hit(&shadow_ray, Interval(BIAS..f64::INFINITY))
+    else {
+        return Color::black();
+    };
+    if light.t > 2.0 * BIAS && root.occluded(&shadow_ray, Interval(BIAS..light.t - BIAS)) {
+        return Color::black();
+    }
+
+    let weight = power_heuristic(sample.pdf, hit.material.pdf(hit, wo, &wi));
+    light.material.emitted(light.u, light.v, &light.point)
+        * f
+        * (wi.dot(&hit.normal).abs() * weight / sample.pdf)
+}
+
+/// Whether the closest hit along `ray` at `t` is on one of the lights of the camera.
+fn is_on_light(camera: &Camera, ray: &Ray, t: f64) -> bool {
+    camera
+        .lights
+        .hit(ray, Interval(t - BIAS..t + BIAS))
+        .is_some()
+}
+
+/// Veach's power heuristic for weighting a sample drawn with density `pdf` against another
+/// strategy that would have drawn it with density `other_pdf`.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let pdf_sq = pdf * pdf;
+
+    pdf_sq / other_pdf.mul_add(other_pdf, pdf_sq)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::background::background_color::BackgroundColor;
+    use crate::camera::{Camera, CameraBuilder};
+    use crate::color::Color;
+    use crate::hit::lights::Lights;
+    use crate::hit::quad::Quad;
+    use crate::hit::scene::Scene;
+    use crate::hit::sphere::SphereBuilder;
+    use crate::hit::Hit;
+    use crate::image::Image;
+    use crate::integrator::brute_force::BruteForce;
+    use crate::integrator::{Integrator, LightSource, PathEnd, PathEvent};
+    use crate::material::dielectric::Dielectric;
+    use crate::material::lambertian::Lambertian;
+    use crate::material::light::DiffuseLight;
+    use crate::material::metal::Metal;
+    use crate::material::Material;
+    use crate::ray::Ray;
+    use crate::sampler::{SampleId, SampleStream};
+    use crate::vec::Vec3;
+
+    use super::PathTracer;
+
+    fn camera(builder: CameraBuilder) -> Camera {
+        builder.build(&Image::with_dimensions(1, 1, Color::black()))
+    }
+
+    fn sample_id(index: u32, count: u32) -> SampleId {
+        SampleId {
+            seed: 0,
+            pixel: (0, 0),
+            index,
+            count,
+        }
+    }
+
+    fn average(
+        integrator: &dyn Integrator,
+        camera: &Camera,
+        root: &dyn Hit,
+        ray: Ray,
+        samples: u32,
+    ) -> Color {
+        let mut color = Color::black();
+        for index in 0..samples {
+            let mut samples = SampleStream::new(camera.sampler.as_ref(), sample_id(index, samples));
+            color += integrator.radiance(camera, root, ray, &mut samples, &mut |_| {});
+        }
+
+        color / f64::from(samples)
+    }
+
+    /// Average the light reflected by a floor under a small light, once with and once without
+    /// sampling the light directly.
+    fn floor_under_light(material: Arc<dyn Material>) -> (f64, f64) {
+        let light: Arc<dyn Hit> = Arc::new(Quad::new(
+            Vec3(-0.5, 1.0, -0.5),
+            Vec3(1.0, 0.0, 0.0),
+            Vec3(0.0, 0.0, 1.0),
+            Arc::new(DiffuseLight::colored(Color::white())),
+        ));
+        let floor = Arc::new(Quad::new(
+            Vec3(-5.0, 0.0, -5.0),
+            Vec3(10.0, 0.0, 0.0),
+            Vec3(0.0, 0.0, 10.0),
+            material,
+        ));
+        let root = Scene::default().add(light.clone()).add(floor);
+        let camera = camera(
+            CameraBuilder::default()
+                .with_max_bounces(2)
+                .with_lights(Lights::new(vec![light])),
+        );
+        let ray = Ray {
+            origin: Vec3(0.3, 0.5, 0.2),
+            direction: Vec3(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+
+        (
+            average(&BruteForce, &camera, &root, ray, 20_000).r(),
+            average(&PathTracer, &camera, &root, ray, 20_000).r(),
+        )
+    }
+
+    #[test]
+    fn light_sampling_is_unbiased() {
+        let (expected, actual) =
+            floor_under_light(Arc::new(Lambertian::colored(Color::new(0.5, 0.5, 0.5))));
+        assert_abs_diff_eq!(expected, actual, epsilon = 0.01);
+
+        let (expected, actual) = floor_under_light(Arc::new(Metal {
+            albedo: Color::new(0.5, 0.5, 0.5),
+            fuzz: 0.3,
+        }));
+        assert_abs_diff_eq!(expected, actual, epsilon = 0.01);
+    }
+
+    /// Render a sphere in a uniformly white environment, where every path that leaves the sphere
+    /// again carries exactly the fraction of light the material reflects.
+    fn furnace(material: Arc<dyn Material>) -> Color {
+        let root = SphereBuilder::new(Vec3::zero(), 1.0, material).build();
+        let camera = camera(CameraBuilder::default().with_background(BackgroundColor::white()));
+        let ray = Ray {
+            origin: Vec3(0.3, 0.2, -5.0),
+            direction: Vec3(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        average(&PathTracer, &camera, &root, ray, 1000)
+    }
+
+    #[test]
+    fn furnace_test() {
+        let albedo = Color::new(0.2, 0.5, 0.8);
+        let emission = Color::new(2.0, 3.0, 4.0);
+
+        assert_abs_diff_eq!(
+            furnace(Arc::new(Lambertian::colored(albedo))),
+            albedo,
+            epsilon = 1e-9
+        );
+        for fuzz in [0.0, 0.3, 1.5] {
+            assert_abs_diff_eq!(
+                furnace(Arc::new(Metal { albedo, fuzz })),
+                albedo,
+                epsilon = 1e-9
+            );
+        }
+        assert_abs_diff_eq!(
+            furnace(Arc::new(Dielectric {
+                refraction_index: 1.5
+            })),
+            Color::white(),
+            epsilon = 1e-9
+        );
+        assert_abs_diff_eq!(
+            furnace(Arc::new(DiffuseLight::colored(emission))),
+            emission,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn russian_roulette_is_unbiased() {
+        let walls = SphereBuilder::new(
+            Vec3::zero(),
+            2.0,
+            Arc::new(Lambertian::colored(Color::new(0.7, 0.7, 0.7))),
+        )
+        .build();
+        let lamp = SphereBuilder::new(
+            Vec3(0.0, 1.0, 0.0),
+            0.5,
+            Arc::new(DiffuseLight::colored(Color::white())),
+        )
+        .build();
+        let root = Scene::default().add(Arc::new(walls)).add(Arc::new(lamp));
+        let ray = Ray {
+            origin: Vec3::zero(),
+            direction: Vec3(0.0, -1.0, 0.0),
+            time: 0.0,
+        };
+
+        let average = |roulette_depth| {
+            let camera = camera(
+                CameraBuilder::default()
+                    .with_max_bounces(20)
+                    .with_roulette_depth(roulette_depth),
+            );
+
+            average(&PathTracer, &camera, &root, ray, 20_000).r()
+        };
+
+        assert_abs_diff_eq!(average(0), average(20), epsilon = 0.015);
+    }
+
+    #[test]
+    fn events() {
+        let root = SphereBuilder::new(
+            Vec3::zero(),
+            1.0,
+            Arc::new(Lambertian::colored(Color::new(0.5, 0.5, 0.5))),
+        )
+        .build();
+        let camera = camera(CameraBuilder::default().with_background(BackgroundColor::white()));
+        let ray = Ray {
+            origin: Vec3(0.0, 0.0, -5.0),
+            direction: Vec3(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+
+        let mut events = Vec::new();
+        let mut samples = SampleStream::new(camera.sampler.as_ref(), sample_id(0, 1));
+        let radiance = PathTracer.radiance(&camera, &root, ray, &mut samples, &mut |event| {
+            events.push(match event {
+                PathEvent::Hit { bounces, hit, .. } => {
+                    assert_abs_diff_eq!(hit.point, Vec3(0.0, 0.0, -1.0));
+                    format!("hit {bounces}")
+                }
+                PathEvent::Light {
+                    bounces,
+                    source,
+                    radiance,
+                } => {
+                    assert_eq!(source, LightSource::Background);
+                    assert_abs_diff_eq!(radiance, Color::new(0.5, 0.5, 0.5), epsilon = 1e-9);
+                    format!("light {bounces}")
+                }
+                PathEvent::End { bounces, reason } => {
+                    assert_eq!(reason, PathEnd::Escaped);
+                    format!("end {bounces}")
+                }
+            });
+        });
+
+        assert_abs_diff_eq!(radiance, Color::new(0.5, 0.5, 0.5), epsilon = 1e-9);
+        assert_eq!(events, ["hit 0", "light 1", "end 1"]);
+    }
+
+    #[test]
+    fn deep_paths() {
+        let mirror = SphereBuilder::new(
+            Vec3::zero(),
+            1.0,
+            Arc::new(Metal {
+                albedo: Color::white(),
+                fuzz: 0.0,
+            }),
+        )
+        .build();
+        let camera = camera(CameraBuilder::default().with_max_bounces(100_000));
+        let ray = Ray {
+            origin: Vec3::zero(),
+            direction: Vec3(0.3, 0.4, 0.5),
+            time: 0.0,
+        };
+
+        let mut end = None;
+        let mut samples = SampleStream::new(camera.sampler.as_ref(), sample_id(0, 1));
+        PathTracer.radiance(&camera, &mirror, ray, &mut samples, &mut |event| {
+            if let PathEvent::End { bounces, reason } = event {
+                end = Some((bounces, reason));
+            }
+        });
+
+        assert_eq!(end, Some((100_000, PathEnd::MaxBounces)));
+    }
+}
@@ -0,0 +1,72 @@
+//! A homebrew path tracer.
+//!
+//! A scene is a hierarchy of objects implementing [`Hit`](hit::Hit), usually collected in a
+//! bounding volume hierarchy ([`Node`](hit::bvh::Node)). Objects reference a
+//! [`Material`](material::Material), which in turn may use a [`Texture`](texture::Texture). The
+//! [`Camera`](camera::Camera), created with a [`CameraBuilder`](camera::CameraBuilder), holds the
+//! render settings and the [`Background`](background::Background) of the scene, and estimates the
+//! light arriving along its rays with an [`Integrator`](integrator::Integrator).
+//!
+//! Scenes can either be built in code or loaded from a RON file with
+//! [`SceneFile`](scene_file::SceneFile). A [`Renderer`](render::Renderer) renders a scene into an
+//! [`Image`](image::Image), which can then be written to disk.
+//!
+//! ```no_run
+//! use std::sync::{mpsc, Arc};
+//!
+//! use raytracer::camera::CameraBuilder;
+//! use raytracer::color::Color;
+//! use raytracer::hit::bvh::Node;
+//! use raytracer::hit::sphere::SphereBuilder;
+//! use raytracer::hit::Hit;
+//! use raytracer::image::Image;
+//! use raytracer::material::lambertian::Lambertian;
+//! use raytracer::render::Renderer;
+//! use raytracer::vec::Vec3;
+//!
+//! let target = Image::with_aspect_ratio(256, 16.0 / 9.0, Color::black());
+//! let camera = CameraBuilder::new(1.0, 0.0, 90.0)
+//!     .with_samples(16)
+//!     .build(&target);
+//! let root: Arc<dyn Hit> = Arc::new(Node::new(vec![Arc::new(
+//!     SphereBuilder::new(
+//!         Vec3(0.0, 0.0, -1.0),
+//!         0.5,
+//!         Arc::new(Lambertian::colored(Color::new(0.8, 0.3, 0.3))),
+//!     )
+//!     .build(),
+//! )]));
+//!
+//! let (samples_tx, _) = mpsc::channel();
+//! let result = Renderer::new(4).render(&camera, &root, &target, &samples_tx);
+//! result
+//!     .image
+//!     .write_png("output/sphere.png", true)
+//!     .expect("could not write image");
+//! println!("{}", result.stats);
+//! ```
+#![warn(clippy::all, clippy::pedantic, clippy::nursery, clippy::cargo)]
+#![allow(clippy::module_name_repetitions)]
+#![allow(clippy::multiple_crate_versions)]
+#![allow(clippy::must_use_candidate, clippy::return_self_not_must_use)]
+
+pub mod background;
+pub mod camera;
+pub mod color;
+pub mod hit;
+pub mod image;
+pub mod integrator;
+pub mod material;
+pub mod math;
+pub mod obj_file;
+mod progress;
+pub mod ray;
+pub mod render;
+pub mod sampler;
+pub mod scene_file;
+pub mod texture;
+pub mod tone_map;
+#[cfg(feature = "preview")]
+pub mod ui;
+pub mod vec;
+mod viewport;
@@ -1,26 +1,80 @@
+//! How light interacts with the surface of an object.
+//!
+//! Directions are given as unit vectors pointing away from the surface: `wo` towards where the
+//! light is going, which is back along the incoming ray, and `wi` towards where it comes from.
+
 use crate::color::Color;
 use crate::hit::HitResult;
-use crate::ray::Ray;
+use crate::sampler::SampleStream;
 use crate::vec::Vec3;
 
 pub mod dielectric;
+pub mod id;
 pub mod lambertian;
 pub mod light;
 pub mod metal;
 
-pub trait Material: Send + Sync {
-    /// Scatter an incoming ray and produce an outgoing ray and attenuation
+/// A direction sampled by [`Material::sample`].
+#[derive(Debug, Clone, Copy)]
+pub struct Scatter {
+    /// The direction `wi` the light arrives from.
+    pub direction: Vec3,
+    /// The BSDF for this direction.
+    pub f: Color,
+    /// The probability density of sampling this direction, with respect to solid angle.
     ///
-    /// # Arguments
+    /// For specular directions the BSDF is a delta distribution, which [`Scatter::f`] already
+    /// accounts for, so this is the probability of having picked this particular direction.
+    pub pdf: f64,
+    /// Whether the direction is the only one the light could have come from, as in a mirror.
+    pub specular: bool,
+}
+
+impl Scatter {
+    /// The fraction of the light arriving from [`Scatter::direction`] that is scattered towards
+    /// `wo`, divided by the density of sampling it.
+    pub fn weight(&self, normal: &Vec3) -> Color {
+        self.f * (self.direction.dot(normal).abs() / self.pdf)
+    }
+}
+
+/// The surface properties of an object.
+pub trait Material: Send + Sync {
+    /// Evaluate the BSDF f(wi, wo) for light arriving from `wi` and leaving towards `wo`.
     ///
-    /// * `ray`: The incoming ray.
-    /// * `hit`: Where the ray hit the object.
+    /// This is always black for specular materials, as the chance of hitting their exact
+    /// directions is zero.
+    fn eval(&self, _hit: &HitResult, _wo: &Vec3, _wi: &Vec3) -> Color {
+        Color::black()
+    }
+
+    /// Sample a direction for the light leaving towards `wo` with the numbers from `samples`, or
+    /// `None` if the material absorbs all light.
+    fn sample(&self, hit: &HitResult, wo: &Vec3, samples: &mut SampleStream) -> Option<Scatter>;
+
+    /// The probability density of [`Material::sample`] producing `wi`, with respect to solid
+    /// angle.
     ///
-    /// Returns a tuple with the new outgoing ray and its attenuation or `None` if the ray was
-    /// absorbed.
-    fn scatter(&self, ray: &Ray, hit: &HitResult) -> Option<(Ray, Color)>;
+    /// Specular materials return 0.
+    fn pdf(&self, _hit: &HitResult, _wo: &Vec3, _wi: &Vec3) -> f64 {
+        0.0
+    }
 
     fn emitted(&self, _u: f64, _v: f64, _point: &Vec3) -> Color {
         Color::black()
     }
+
+    /// The color of the surface at `hit`, the fraction of light it reflects or transmits
+    /// without regard to direction.
+    ///
+    /// Only used for the albedo AOV, which denoisers use to tell texture detail from noise.
+    fn albedo(&self, _hit: &HitResult) -> Color {
+        Color::black()
+    }
+
+    /// The ID of the material in the material ID AOV, 0 unless it was given one with
+    /// [`MaterialId`](id::MaterialId).
+    fn id(&self) -> u32 {
+        0
+    }
 }
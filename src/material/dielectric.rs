@@ -1,44 +1,41 @@
 use crate::color::Color;
 use crate::hit::HitResult;
-use crate::material::Material;
-use crate::ray::Ray;
+use crate::material::{Material, Scatter};
+use crate::sampler::SampleStream;
+use crate::vec::Vec3;
 
 pub struct Dielectric {
     pub refraction_index: f64,
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &HitResult) -> Option<(Ray, Color)> {
+    fn sample(&self, hit: &HitResult, wo: &Vec3, samples: &mut SampleStream) -> Option<Scatter> {
         let refraction_ratio = if hit.front_face {
             1.0 / self.refraction_index
         } else {
             self.refraction_index
         };
-        let direction_normalized = ray.direction.normalized();
-        let cos_theta = -direction_normalized.dot(&hit.normal).min(1.0);
+        let cos_theta = wo.dot(&hit.normal).min(1.0);
         let sin_theta = cos_theta.mul_add(-cos_theta, 1.0).sqrt();
 
-        if refraction_ratio * sin_theta > 1.0 || self.reflectance(cos_theta) > rand::random() {
-            // reflect
-            Some((
-                Ray {
-                    origin: hit.point,
-                    direction: direction_normalized.reflect(&hit.normal),
-                    time: ray.time,
-                },
-                Color::white(),
-            ))
+        let direction = if refraction_ratio * sin_theta > 1.0
+            || self.reflectance(cos_theta) > samples.next_1d()
+        {
+            (-*wo).reflect(&hit.normal)
         } else {
-            // refract
-            Some((
-                Ray {
-                    origin: hit.point,
-                    direction: direction_normalized.refract(&hit.normal, refraction_ratio),
-                    time: ray.time,
-                },
-                Color::white(),
-            ))
-        }
+            (-*wo).refract(&hit.normal, refraction_ratio)
+        };
+
+        Some(Scatter {
+            direction,
+            f: Color::white() / direction.dot(&hit.normal).abs(),
+            pdf: 1.0,
+            specular: true,
+        })
+    }
+
+    fn albedo(&self, _hit: &HitResult) -> Color {
+        Color::white()
     }
 }
 
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hit::HitResult;
+use crate::material::{Material, Scatter};
+use crate::sampler::SampleStream;
+use crate::vec::Vec3;
+
+/// Gives a material an ID, so the surfaces using it can be told apart from the others in the
+/// material ID AOV. Otherwise it behaves exactly like the material it wraps.
+pub struct MaterialId {
+    material: Arc<dyn Material>,
+    id: u32,
+}
+
+impl MaterialId {
+    pub fn new(material: Arc<dyn Material>, id: u32) -> Self {
+        Self { material, id }
+    }
+}
+
+impl Material for MaterialId {
+    fn eval(&self, hit: &HitResult, wo: &Vec3, wi: &Vec3) -> Color {
+        self.material.eval(hit, wo, wi)
+    }
+
+    fn sample(&self, hit: &HitResult, wo: &Vec3, samples: &mut SampleStream) -> Option<Scatter> {
+        self.material.sample(hit, wo, samples)
+    }
+
+    fn pdf(&self, hit: &HitResult, wo: &Vec3, wi: &Vec3) -> f64 {
+        self.material.pdf(hit, wo, wi)
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: &Vec3) -> Color {
+        self.material.emitted(u, v, point)
+    }
+
+    fn albedo(&self, hit: &HitResult) -> Color {
+        self.material.albedo(hit)
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
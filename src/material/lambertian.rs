@@ -1,11 +1,12 @@
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 use approx::abs_diff_eq;
 
 use crate::color::Color;
 use crate::hit::HitResult;
-use crate::material::Material;
-use crate::ray::Ray;
+use crate::material::{Material, Scatter};
+use crate::sampler::SampleStream;
 use crate::texture::solid_color::SolidColor;
 use crate::texture::Texture;
 use crate::vec::Vec3;
@@ -23,20 +24,35 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, ray: &Ray, hit: &HitResult) -> Option<(Ray, Color)> {
-        let mut direction = hit.normal + Vec3::random_unit_vector();
+    fn eval(&self, hit: &HitResult, _wo: &Vec3, wi: &Vec3) -> Color {
+        if wi.dot(&hit.normal) > 0.0 {
+            self.texture.value(hit.u, hit.v, &hit.point) / PI
+        } else {
+            Color::black()
+        }
+    }
+
+    fn sample(&self, hit: &HitResult, wo: &Vec3, samples: &mut SampleStream) -> Option<Scatter> {
+        let mut direction = hit.normal + Vec3::on_unit_sphere(samples.next_2d());
 
         if abs_diff_eq!(direction, Vec3::zero()) {
             direction = hit.normal;
         }
+        direction.normalize();
+
+        Some(Scatter {
+            direction,
+            f: self.eval(hit, wo, &direction),
+            pdf: self.pdf(hit, wo, &direction),
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &HitResult, _wo: &Vec3, wi: &Vec3) -> f64 {
+        wi.dot(&hit.normal).max(0.0) / PI
+    }
 
-        Some((
-            Ray {
-                origin: hit.point,
-                direction,
-                time: ray.time,
-            },
-            self.texture.value(hit.u, hit.v, &hit.point),
-        ))
+    fn albedo(&self, hit: &HitResult) -> Color {
+        self.texture.value(hit.u, hit.v, &hit.point)
     }
 }
@@ -2,8 +2,8 @@ use std::sync::Arc;
 
 use crate::color::Color;
 use crate::hit::HitResult;
-use crate::material::Material;
-use crate::ray::Ray;
+use crate::material::{Material, Scatter};
+use crate::sampler::SampleStream;
 use crate::texture::solid_color::SolidColor;
 use crate::texture::Texture;
 use crate::vec::Vec3;
@@ -21,7 +21,7 @@ impl DiffuseLight {
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _: &Ray, _: &HitResult) -> Option<(Ray, Color)> {
+    fn sample(&self, _: &HitResult, _: &Vec3, _: &mut SampleStream) -> Option<Scatter> {
         None
     }
 
@@ -1,7 +1,9 @@
+use std::f64::consts::PI;
+
 use crate::color::Color;
 use crate::hit::HitResult;
-use crate::material::Material;
-use crate::ray::Ray;
+use crate::material::{Material, Scatter};
+use crate::sampler::SampleStream;
 use crate::vec::Vec3;
 
 pub struct Metal {
@@ -10,24 +12,145 @@ pub struct Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &HitResult) -> Option<(Ray, Color)> {
+    fn eval(&self, hit: &HitResult, wo: &Vec3, wi: &Vec3) -> Color {
+        let cosine = wi.dot(&hit.normal);
+        if cosine <= 0.0 {
+            return Color::black();
+        }
+
+        self.albedo * (self.pdf(hit, wo, wi) / cosine)
+    }
+
+    fn sample(&self, hit: &HitResult, wo: &Vec3, samples: &mut SampleStream) -> Option<Scatter> {
+        let reflected = (-*wo).reflect(&hit.normal);
+        if self.fuzz <= 0.0 {
+            return Some(Scatter {
+                direction: reflected,
+                f: self.albedo / reflected.dot(&hit.normal).abs(),
+                pdf: 1.0,
+                specular: true,
+            });
+        }
+
         loop {
-            let mut scattered = ray.direction.normalized().reflect(&hit.normal);
-            if self.fuzz > 0.0 {
-                scattered += self.fuzz * Vec3::random_unit_vector();
-                scattered.normalize();
-            }
+            let direction =
+                (reflected + self.fuzz * Vec3::on_unit_sphere(samples.next_2d())).normalized();
 
-            if scattered.dot(&hit.normal) > 0.0 {
-                return Some((
-                    Ray {
-                        origin: hit.point,
-                        direction: scattered,
-                        time: ray.time,
-                    },
-                    self.albedo,
-                ));
+            if direction.dot(&hit.normal) > 0.0 {
+                return Some(Scatter {
+                    direction,
+                    f: self.eval(hit, wo, &direction),
+                    pdf: self.pdf(hit, wo, &direction),
+                    specular: false,
+                });
             }
         }
     }
+
+    fn pdf(&self, hit: &HitResult, wo: &Vec3, wi: &Vec3) -> f64 {
+        if self.fuzz <= 0.0 || wi.dot(&hit.normal) <= 0.0 {
+            return 0.0;
+        }
+
+        // the sampled directions point at uniformly distributed points on a sphere with radius
+        // `fuzz` around the tip of the reflected direction, so find where `wi` crosses the sphere
+        let reflected = (-*wo).reflect(&hit.normal);
+        let projection = wi.dot(&reflected);
+        let discriminant = projection.mul_add(projection, self.fuzz.mul_add(self.fuzz, -1.0));
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let root = discriminant.sqrt();
+        let density = [projection - root, projection + root]
+            .into_iter()
+            .filter(|&t| t > 0.0)
+            .map(|t| t * t / (4.0 * PI * self.fuzz * root))
+            .sum::<f64>();
+
+        // points whose direction ends up below the surface are rejected and drawn again
+        let accepted = (0.5 + 0.5 * reflected.dot(&hit.normal) / self.fuzz).min(1.0);
+
+        density / accepted
+    }
+
+    fn albedo(&self, _hit: &HitResult) -> Color {
+        self.albedo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::HitResult;
+    use crate::material::Material;
+    use crate::sampler::independent::Independent;
+    use crate::sampler::{SampleId, SampleStream};
+    use crate::vec::Vec3;
+
+    use super::Metal;
+
+    /// Check that the density integrates to one by averaging its inverse over its own samples,
+    /// which gives the solid angle the samples cover.
+    fn covered_solid_angle(metal: Metal, wo: Vec3) -> f64 {
+        let metal = Arc::new(metal);
+        let hit = HitResult {
+            point: Vec3::zero(),
+            normal: Vec3(0.0, 1.0, 0.0),
+            t: 1.0,
+            u: 0.0,
+            v: 0.0,
+            front_face: true,
+            material: metal.clone(),
+            object_id: 0,
+        };
+
+        let samples = 100_000;
+        let mut solid_angle = 0.0;
+        for index in 0..samples {
+            let mut stream = SampleStream::new(
+                &Independent,
+                SampleId {
+                    seed: 0,
+                    pixel: (0, 0),
+                    index,
+                    count: samples,
+                },
+            );
+            let scatter = metal
+                .sample(&hit, &wo, &mut stream)
+                .expect("metal should reflect");
+            solid_angle += 1.0 / scatter.pdf;
+        }
+
+        solid_angle / f64::from(samples)
+    }
+
+    #[test]
+    fn pdf() {
+        let metal = Metal {
+            albedo: Color::white(),
+            fuzz: 0.5,
+        };
+        let cone = 2.0 * PI * (1.0 - (PI / 6.0).cos());
+        assert_abs_diff_eq!(
+            covered_solid_angle(metal, Vec3(0.0, 1.0, 0.0)),
+            cone,
+            epsilon = 0.01
+        );
+
+        let metal = Metal {
+            albedo: Color::white(),
+            fuzz: 2.0,
+        };
+        assert_abs_diff_eq!(
+            covered_solid_angle(metal, Vec3(-1.0, 1.0, 0.0).normalized()),
+            2.0 * PI,
+            epsilon = 0.05
+        );
+    }
 }
@@ -1,13 +1,16 @@
+//! Mathematical helpers.
+
 pub mod aabb;
 pub mod interval;
+pub mod onb;
 pub mod perlin;
 
 pub fn clamp_repeating(x: f64) -> f64 {
     let mut rem = x % 1.0;
-    
+
     if rem < 0.0 {
         rem += 1.0;
     }
-    
+
     rem
 }
@@ -7,9 +7,8 @@ use crate::vec::Vec3;
 #[derive(Default, Clone)]
 pub struct Aabb(pub Interval, pub Interval, pub Interval);
 
-#[allow(dead_code)]
 impl Aabb {
-    pub fn with_extrema(a: Vec3, b: Vec3) -> Self {
+    pub const fn with_extrema(a: Vec3, b: Vec3) -> Self {
         Self(
             Interval(a.0.min(b.0)..a.0.max(b.0)),
             Interval(a.1.min(b.1)..a.1.max(b.1)),
@@ -17,6 +16,15 @@ impl Aabb {
         )
     }
 
+    /// A box that contains nothing and becomes the other box when combined with it.
+    pub const fn empty() -> Self {
+        Self(
+            Interval(f64::INFINITY..f64::NEG_INFINITY),
+            Interval(f64::INFINITY..f64::NEG_INFINITY),
+            Interval(f64::INFINITY..f64::NEG_INFINITY),
+        )
+    }
+
     pub fn pad(&mut self, delta: f64) {
         self.0.pad(delta);
         self.1.pad(delta);
@@ -45,13 +53,13 @@ impl Aabb {
         )
     }
 
-    pub fn combine(&mut self, rhs: &Self) {
+    pub const fn combine(&mut self, rhs: &Self) {
         self.0.combine(&rhs.0);
         self.1.combine(&rhs.1);
         self.2.combine(&rhs.2);
     }
 
-    pub fn combined(&self, rhs: &Self) -> Self {
+    pub const fn combined(&self, rhs: &Self) -> Self {
         Self(
             self.0.combined(&rhs.0),
             self.1.combined(&rhs.1),
@@ -59,6 +67,11 @@ impl Aabb {
         )
     }
 
+    /// The interval along axis `n` (0 = x, 1 = y, 2 = z).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not a valid axis.
     pub fn axis(&self, n: u32) -> &Interval {
         match n {
             0 => &self.0,
@@ -68,6 +81,24 @@ impl Aabb {
         }
     }
 
+    pub const fn centroid(&self) -> Vec3 {
+        Vec3(
+            f64::midpoint(self.0.start(), self.0.end()),
+            f64::midpoint(self.1.start(), self.1.end()),
+            f64::midpoint(self.2.start(), self.2.end()),
+        )
+    }
+
+    /// The surface area of the box, zero if it is empty.
+    pub fn surface_area(&self) -> f64 {
+        let (x, y, z) = (self.0.len(), self.1.len(), self.2.len());
+        if x < 0.0 || y < 0.0 || z < 0.0 {
+            return 0.0;
+        }
+
+        2.0 * x.mul_add(y, x.mul_add(z, y * z))
+    }
+
     pub fn hit(&self, ray: &Ray, t_interval: &mut Interval) -> bool {
         for i in 0..3 {
             let direction_inv = 1.0 / ray.direction.axis(i);
@@ -108,3 +139,21 @@ impl Add<Aabb> for Vec3 {
         rhs + self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::vec::Vec3;
+
+    use super::Aabb;
+
+    #[test]
+    fn surface_area() {
+        let aabb = Aabb::with_extrema(Vec3(0.0, 0.0, 0.0), Vec3(1.0, 2.0, 3.0));
+
+        assert_abs_diff_eq!(aabb.surface_area(), 22.0);
+        assert_abs_diff_eq!(Aabb::empty().surface_area(), 0.0);
+        assert_abs_diff_eq!(Aabb::empty().combined(&aabb).surface_area(), 22.0);
+    }
+}
@@ -17,11 +17,11 @@ impl Interval {
         self.0.end - self.0.start
     }
 
-    pub fn set_start(&mut self, start: f64) {
+    pub const fn set_start(&mut self, start: f64) {
         self.0.start = start;
     }
 
-    pub fn set_end(&mut self, end: f64) {
+    pub const fn set_end(&mut self, end: f64) {
         self.0.end = end;
     }
 
@@ -51,12 +51,12 @@ impl Interval {
         Self(self.start() - half_delta..self.end() + half_delta)
     }
 
-    pub fn combine(&mut self, rhs: &Self) {
+    pub const fn combine(&mut self, rhs: &Self) {
         self.set_start(self.start().min(rhs.start()));
         self.set_end(self.end().max(rhs.end()));
     }
 
-    pub fn combined(&self, rhs: &Self) -> Self {
+    pub const fn combined(&self, rhs: &Self) -> Self {
         Self(self.start().min(rhs.start())..self.end().max(rhs.end()))
     }
 }
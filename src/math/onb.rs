@@ -0,0 +1,51 @@
+use crate::vec::Vec3;
+
+/// An orthonormal basis around a given direction.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Build a basis whose `w` axis points along `normal`.
+    pub fn new(normal: &Vec3) -> Self {
+        let w = normal.normalized();
+        let a = if w.0.abs() > 0.9 {
+            Vec3(0.0, 1.0, 0.0)
+        } else {
+            Vec3(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).normalized();
+        let u = w.cross(&v);
+
+        Self { u, v, w }
+    }
+
+    /// Transform a vector from the coordinates of this basis into world space.
+    pub fn local(&self, a: &Vec3) -> Vec3 {
+        a.0 * self.u + a.1 * self.v + a.2 * self.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::vec::Vec3;
+
+    use super::Onb;
+
+    #[test]
+    fn orthonormal() {
+        let onb = Onb::new(&Vec3(1.0, 2.0, 3.0));
+
+        assert_abs_diff_eq!(onb.w, Vec3(1.0, 2.0, 3.0).normalized());
+        assert_abs_diff_eq!(onb.u.len(), 1.0);
+        assert_abs_diff_eq!(onb.v.len(), 1.0);
+        assert_abs_diff_eq!(onb.u.dot(&onb.v), 0.0);
+        assert_abs_diff_eq!(onb.u.dot(&onb.w), 0.0);
+        assert_abs_diff_eq!(onb.v.dot(&onb.w), 0.0);
+        assert_abs_diff_eq!(onb.local(&Vec3(0.0, 0.0, 1.0)), onb.w);
+    }
+}
@@ -1,4 +1,6 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 
 use crate::vec::Vec3;
 
@@ -10,8 +12,17 @@ pub struct PerlinNoise {
     permutations: (Vec<usize>, Vec<usize>, Vec<usize>),
 }
 
+impl Default for PerlinNoise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
 impl PerlinNoise {
-    pub fn new() -> Self {
+    /// Noise with gradients and permutations chosen by `seed`, so the same seed always gives the
+    /// same noise.
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut points = Vec::with_capacity(POINT_COUNT as usize);
         let mut permutations: (Vec<usize>, Vec<usize>, Vec<usize>) = (
             (0..POINT_COUNT as usize).collect(),
@@ -20,11 +31,11 @@ impl PerlinNoise {
         );
 
         for _ in 0..POINT_COUNT {
-            points.push(Vec3::random_unit_vector());
+            points.push(Vec3::on_unit_sphere((rng.gen(), rng.gen())));
         }
-        permutations.0.shuffle(&mut rand::thread_rng());
-        permutations.1.shuffle(&mut rand::thread_rng());
-        permutations.2.shuffle(&mut rand::thread_rng());
+        permutations.0.shuffle(&mut rng);
+        permutations.1.shuffle(&mut rng);
+        permutations.2.shuffle(&mut rng);
 
         Self {
             points,
@@ -90,3 +101,22 @@ impl PerlinNoise {
         noise.abs()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::vec::Vec3;
+
+    use super::PerlinNoise;
+
+    #[test]
+    fn seeded() {
+        let points = [Vec3(0.3, 1.7, -2.2), Vec3(10.1, 0.5, 4.9)];
+        let noise = |seed| {
+            let perlin = PerlinNoise::new(seed);
+            points.map(|point| perlin.noise(point).to_bits())
+        };
+
+        assert_eq!(noise(5), noise(5));
+        assert_ne!(noise(5), noise(6));
+    }
+}
@@ -0,0 +1,347 @@
+//! Triangle meshes imported from Wavefront OBJ files and their MTL materials.
+
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tobj::{LoadError, GPU_LOAD_OPTIONS};
+
+use crate::color::space::{ColorSpace, WorkingSpace};
+use crate::color::Color;
+use crate::hit::mesh::{Mesh, MeshBuilder, MeshError};
+use crate::image::ImageError;
+use crate::material::dielectric::Dielectric;
+use crate::material::lambertian::Lambertian;
+use crate::material::light::DiffuseLight;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::texture::image::ImageTexture;
+use crate::vec::Vec3;
+
+/// Refraction index of transparent materials that don't specify `Ni`.
+const DEFAULT_REFRACTION_INDEX: f64 = 1.5;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Parse(LoadError),
+    Materials(LoadError),
+    Texture { path: PathBuf, error: ImageError },
+    Mesh { name: String, error: MeshError },
+    NoFaces,
+}
+
+impl Display for ObjError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => write!(f, "could not load model: {error}"),
+            Self::Materials(error) => write!(f, "could not load materials: {error}"),
+            Self::Texture { path, error } => {
+                write!(f, "could not load texture {}: {error}", path.display())
+            }
+            Self::Mesh { name, error } => write!(f, "invalid mesh `{name}`: {error}"),
+            Self::NoFaces => write!(f, "model has no faces"),
+        }
+    }
+}
+
+/// A model loaded from an OBJ file.
+///
+/// Faces are triangulated on load. Every object or group in the file becomes its own mesh with
+/// the material it uses in the MTL file.
+pub struct ObjFile {
+    models: Vec<tobj::Model>,
+    materials: Vec<tobj::Material>,
+    directory: PathBuf,
+    working_space: WorkingSpace,
+}
+
+impl ObjFile {
+    /// Read and parse an OBJ file and the MTL files it refers to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model or its materials can't be read or parsed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ObjError> {
+        let (models, materials) =
+            tobj::load_obj(path.as_ref(), &GPU_LOAD_OPTIONS).map_err(ObjError::Parse)?;
+        let directory = path
+            .as_ref()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        Ok(Self {
+            models,
+            materials: materials.map_err(ObjError::Materials)?,
+            directory,
+            working_space: WorkingSpace::LinearSrgb,
+        })
+    }
+
+    /// Parse an OBJ model, resolving MTL files and textures relative to `directory`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the model or its materials can't be read or parsed.
+    pub fn parse<R: BufRead>(mut source: R, directory: PathBuf) -> Result<Self, ObjError> {
+        let (models, materials) = tobj::load_obj_buf(&mut source, &GPU_LOAD_OPTIONS, |path| {
+            tobj::load_mtl(directory.join(path))
+        })
+        .map_err(ObjError::Parse)?;
+
+        Ok(Self {
+            models,
+            materials: materials.map_err(ObjError::Materials)?,
+            directory,
+            working_space: WorkingSpace::LinearSrgb,
+        })
+    }
+
+    /// Convert the colors and textures of the materials into `working_space` when building them.
+    pub const fn with_working_space(mut self, working_space: WorkingSpace) -> Self {
+        self.working_space = working_space;
+        self
+    }
+
+    /// Build a mesh for every object in the file.
+    ///
+    /// If `material` is given, it is used for all meshes instead of the materials from the MTL
+    /// files. Objects without a material are gray and diffuse.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a texture can't be loaded, a mesh is invalid or there are no faces at
+    /// all.
+    pub fn build(&self, material: Option<Arc<dyn Material>>) -> Result<Vec<Mesh>, ObjError> {
+        let materials = self
+            .materials
+            .iter()
+            .map(|mtl| {
+                material
+                    .as_ref()
+                    .map_or_else(|| self.build_material(mtl), |material| Ok(material.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let fallback =
+            material.unwrap_or_else(|| Arc::new(Lambertian::colored(Color::new(0.8, 0.8, 0.8))));
+
+        let meshes = self
+            .models
+            .iter()
+            .filter(|model| !model.mesh.indices.is_empty())
+            .map(|model| {
+                let material = model
+                    .mesh
+                    .material_id
+                    .and_then(|id| materials.get(id))
+                    .unwrap_or(&fallback)
+                    .clone();
+
+                build_mesh(&model.mesh, material).map_err(|error| ObjError::Mesh {
+                    name: model.name.clone(),
+                    error,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if meshes.is_empty() {
+            Err(ObjError::NoFaces)
+        } else {
+            Ok(meshes)
+        }
+    }
+
+    /// Map an MTL material onto the closest material of the renderer.
+    ///
+    /// Materials with an emission (`Ke`) become lights, transparent ones (`d` below one or an
+    /// illumination model with refraction) become dielectrics and ones with an illumination model
+    /// with reflection become metals. Everything else is diffuse.
+    fn build_material(&self, mtl: &tobj::Material) -> Result<Arc<dyn Material>, ObjError> {
+        if let Some(emission) = mtl.unknown_param.get("Ke").and_then(|ke| parse_color(ke)) {
+            if emission != Color::black() {
+                return Ok(Arc::new(DiffuseLight::colored(
+                    self.working_space.from_linear_srgb(emission),
+                )));
+            }
+        }
+
+        let illumination_model = mtl.illumination_model.unwrap_or(2);
+        if mtl.dissolve.is_some_and(|dissolve| dissolve < 1.0)
+            || matches!(illumination_model, 4 | 6 | 7 | 9)
+        {
+            return Ok(Arc::new(Dielectric {
+                refraction_index: mtl
+                    .optical_density
+                    .map_or(DEFAULT_REFRACTION_INDEX, f64::from),
+            }));
+        }
+
+        let diffuse = mtl.diffuse.map_or(Color::new(0.8, 0.8, 0.8), color);
+        if matches!(illumination_model, 3 | 5) {
+            return Ok(Arc::new(Metal {
+                albedo: self
+                    .working_space
+                    .from_linear_srgb(mtl.specular.map_or(diffuse, color)),
+                // approximate the roughness of a Phong lobe with the given exponent
+                fuzz: mtl
+                    .shininess
+                    .map_or(0.0, |shininess| (2.0 / (f64::from(shininess) + 2.0)).sqrt()),
+            }));
+        }
+
+        Ok(match &mtl.diffuse_texture {
+            Some(texture) => {
+                let path = self.directory.join(texture);
+                let texture =
+                    ImageTexture::load(&path, ColorSpace::for_file(&path), self.working_space)
+                        .map_err(|error| ObjError::Texture { path, error })?;
+
+                Arc::new(Lambertian {
+                    texture: Arc::new(texture),
+                })
+            }
+            None => Arc::new(Lambertian::colored(
+                self.working_space.from_linear_srgb(diffuse),
+            )),
+        })
+    }
+}
+
+fn build_mesh(mesh: &tobj::Mesh, material: Arc<dyn Material>) -> Result<Mesh, MeshError> {
+    let mut builder = MeshBuilder::new(
+        mesh.positions.chunks_exact(3).map(vec3).collect(),
+        mesh.indices
+            .chunks_exact(3)
+            .map(|face| [face[0], face[1], face[2]].map(|index| index as usize))
+            .collect(),
+        material,
+    );
+    if !mesh.normals.is_empty() {
+        builder = builder.with_normals(
+            mesh.normals
+                .chunks_exact(3)
+                .map(|normal| vec3(normal).normalized())
+                .collect(),
+        );
+    }
+    if !mesh.texcoords.is_empty() {
+        builder = builder.with_uvs(
+            mesh.texcoords
+                .chunks_exact(2)
+                .map(|uv| (f64::from(uv[0]), f64::from(uv[1])))
+                .collect(),
+        );
+    }
+
+    builder.build()
+}
+
+fn vec3(values: &[f32]) -> Vec3 {
+    Vec3(
+        f64::from(values[0]),
+        f64::from(values[1]),
+        f64::from(values[2]),
+    )
+}
+
+fn color(values: [f32; 3]) -> Color {
+    Color::new(
+        f64::from(values[0]),
+        f64::from(values[1]),
+        f64::from(values[2]),
+    )
+}
+
+fn parse_color(source: &str) -> Option<Color> {
+    let values = source
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<f32>, _>>()
+        .ok()?;
+
+    Some(color(values.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::hit::Hit;
+    use crate::material::lambertian::Lambertian;
+    use crate::math::interval::Interval;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::{ObjError, ObjFile};
+
+    const QUAD: &str = "
+o quad
+v -1.0 -1.0 0.0
+v 1.0 -1.0 0.0
+v 1.0 1.0 0.0
+v -1.0 1.0 0.0
+vt 0.0 0.0
+vt 1.0 0.0
+vt 1.0 1.0
+vt 0.0 1.0
+vn 0.0 0.0 1.0
+f 1/1/1 2/2/1 3/3/1 4/4/1
+";
+
+    #[test]
+    fn triangulated_quad() {
+        let obj = ObjFile::parse(QUAD.as_bytes(), PathBuf::new()).expect("could not parse model");
+        let meshes = obj.build(None).expect("could not build meshes");
+
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].data.faces.len(), 2);
+
+        let hit = meshes[0]
+            .hit(
+                &Ray {
+                    origin: Vec3(0.5, 0.5, 1.0),
+                    direction: Vec3(0.0, 0.0, -1.0),
+                    time: 0.0,
+                },
+                Interval(0.0..f64::INFINITY),
+            )
+            .expect("ray should hit the quad");
+
+        assert_abs_diff_eq!(hit.point, Vec3(0.5, 0.5, 0.0));
+        assert_abs_diff_eq!(hit.normal, Vec3(0.0, 0.0, 1.0));
+        assert_abs_diff_eq!(hit.u, 0.75);
+        assert_abs_diff_eq!(hit.v, 0.75);
+    }
+
+    #[test]
+    fn material_override() {
+        let obj = ObjFile::parse(QUAD.as_bytes(), PathBuf::new()).expect("could not parse model");
+        let material = Arc::new(Lambertian::colored(Color::white()));
+
+        assert!(obj.build(Some(material)).is_ok());
+    }
+
+    #[test]
+    fn malformed() {
+        let error = ObjFile::parse(
+            QUAD.replace("v 1.0 1.0 0.0", "v 1.0 one 0.0").as_bytes(),
+            PathBuf::new(),
+        );
+        assert!(matches!(error, Err(ObjError::Parse(_))));
+
+        let error = ObjFile::parse(
+            format!("mtllib missing.mtl\n{QUAD}").as_bytes(),
+            PathBuf::new(),
+        );
+        assert!(matches!(error, Err(ObjError::Materials(_))));
+
+        let obj =
+            ObjFile::parse(&b"v 0.0 0.0 0.0\n"[..], PathBuf::new()).expect("could not parse model");
+        assert!(matches!(obj.build(None), Err(ObjError::NoFaces)));
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{self, IsTerminal, Write};
+
+/// Reports the progress of a render on the terminal.
+///
+/// On an interactive terminal, the progress is updated in place. Otherwise (e.g. when the output
+/// is redirected into a log file) a new line is written every `LOG_STEP` percent.
+pub struct Progress {
+    total: usize,
+    interactive: bool,
+    reported: Option<u32>,
+}
+
+const LOG_STEP: u32 = 10;
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total: total.max(1),
+            interactive: io::stdout().is_terminal(),
+            reported: None,
+        }
+    }
+
+    /// Report that `done` out of the total units of work are finished.
+    ///
+    /// Nothing is written unless the progress changed enough to be visible.
+    pub fn update(&mut self, done: usize) {
+        let percent = self.percent(done);
+        let step = if self.interactive { 1 } else { LOG_STEP };
+        if self
+            .reported
+            .is_some_and(|reported| percent < reported + step)
+        {
+            return;
+        }
+        self.reported = Some(percent);
+
+        if self.interactive {
+            print!("\rprogress: {percent:>3}%");
+            let _ = io::stdout().flush();
+        } else {
+            println!("progress: {percent:>3}%");
+        }
+    }
+
+    pub fn finish(&mut self) {
+        // always report the end, even if it is less than a step away from the last report
+        if self.reported != Some(100) {
+            self.reported = None;
+        }
+        self.update(self.total);
+        if self.interactive {
+            println!();
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn percent(&self, done: usize) -> u32 {
+        (done.min(self.total) * 100 / self.total) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Progress;
+
+    #[test]
+    fn percent() {
+        let progress = Progress::new(200);
+
+        assert_eq!(progress.percent(0), 0);
+        assert_eq!(progress.percent(3), 1);
+        assert_eq!(progress.percent(200), 100);
+        assert_eq!(progress.percent(300), 100);
+    }
+}
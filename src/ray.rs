@@ -1,3 +1,5 @@
+//! Rays traced through the scene.
+
 use crate::vec::Vec3;
 
 #[derive(Debug, Clone, Copy)]
@@ -7,7 +9,6 @@ pub struct Ray {
     pub time: f64,
 }
 
-#[allow(dead_code)]
 impl Ray {
     pub fn look_at(origin: Vec3, target: Vec3, time: f64) -> Self {
         Self {
@@ -0,0 +1,776 @@
+//! Rendering a scene on multiple threads.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::hit::Hit;
+use crate::image::Image;
+use crate::progress::Progress;
+use crate::render::aov::{Aov, AovPixel, AovSample};
+use crate::render::film::{Film, PixelSamples};
+use crate::render::stats::{RenderStats, StopReason};
+use crate::render::tile::Tile;
+
+pub mod aov;
+pub mod film;
+pub mod stats;
+pub mod tile;
+
+/// Width and height of the tiles the image is split into.
+const TILE_SIZE: u32 = 16;
+
+/// Units the progress towards a [`StoppingCriterion::TargetNoise`] is reported in.
+const NOISE_PROGRESS_STEPS: u32 = 1000;
+
+/// The most samples a pixel can receive in one round of adaptive sampling, as a multiple of the
+/// base samples, so a few very noisy pixels can't use up a whole round.
+const MAX_ROUND_SHARE: u32 = 4;
+
+/// How often a thread waiting for an earlier pass of its tile checks whether the render should
+/// stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// When a render is finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StoppingCriterion {
+    /// Take the camera's number of samples for every pixel.
+    ///
+    /// With adaptive sampling, this is the average number of samples per pixel instead.
+    Samples,
+    /// Keep rendering passes until the time is used up.
+    TimeBudget(Duration),
+    /// Keep rendering passes until the estimated noise of the image drops below this value.
+    ///
+    /// The noise is the standard error of a pixel's average relative to its brightness,
+    /// averaged over the image, e.g. `0.01` for an error of 1 %. It is checked after every
+    /// pass, once every pixel has at least two samples.
+    TargetNoise(f64),
+}
+
+/// Renders scenes on multiple threads.
+///
+/// The image is split into tiles, which the threads take from a shared queue and render into one
+/// shared [`Film`].
+///
+/// The samples of every pixel are added up in the same order no matter which thread renders
+/// them, so with [`StoppingCriterion::Samples`] the result only depends on the camera, including
+/// its [seed](crate::camera::CameraBuilder::with_seed), and not on the number of threads.
+pub struct Renderer {
+    num_threads: u32,
+    progressive: bool,
+    adaptive: Option<u32>,
+    criterion: StoppingCriterion,
+    aovs: Vec<Aov>,
+    log: bool,
+    stop: Arc<AtomicBool>,
+}
+
+/// The result of [`Renderer::render`].
+pub struct RenderOutput {
+    pub image: Image,
+    /// How many samples every pixel received, from black for none to white for the most any
+    /// pixel received.
+    pub sample_map: Image,
+    /// The AOVs requested with [`Renderer::with_aovs`], in the order they were requested.
+    pub aovs: Vec<(Aov, Image)>,
+    pub stats: RenderStats,
+}
+
+impl Renderer {
+    pub fn new(num_threads: u32) -> Self {
+        Self {
+            num_threads: num_threads.max(1),
+            progressive: false,
+            adaptive: None,
+            criterion: StoppingCriterion::Samples,
+            aovs: Vec::new(),
+            log: false,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Render one sample per pixel over the whole image in repeated passes, instead of finishing
+    /// each tile before moving on to the next.
+    ///
+    /// Every pass refines the whole image, so the render can be stopped at any point with a
+    /// usable result.
+    pub const fn with_progressive(mut self, progressive: bool) -> Self {
+        self.progressive = progressive;
+        self
+    }
+
+    /// Spend the samples where the image is noisiest, instead of giving every pixel the same
+    /// number.
+    ///
+    /// Every pixel first receives `base_samples` samples (at least 2) to estimate its noise. The
+    /// rest of the samples is then handed out in rounds, each in proportion to the relative
+    /// error of the pixels after the previous round. Pixels without any noise, like a flat
+    /// background, receive no more samples, and the render ends early once all pixels are like
+    /// that.
+    ///
+    /// This is slightly biased towards darker images: a pixel whose first samples all missed a
+    /// rare bright path looks converged and keeps its too dark average.
+    ///
+    /// The rounds always render progressively.
+    pub const fn with_adaptive(mut self, base_samples: Option<u32>) -> Self {
+        self.adaptive = base_samples;
+        self
+    }
+
+    /// Decide when the render is finished. Defaults to [`StoppingCriterion::Samples`].
+    ///
+    /// The other criteria don't know the number of samples up front, so they always render
+    /// progressively and ignore the camera's number of samples.
+    pub const fn with_stopping_criterion(mut self, criterion: StoppingCriterion) -> Self {
+        self.criterion = criterion;
+        self
+    }
+
+    /// Also render `aovs`, from the same samples as the image.
+    pub fn with_aovs(mut self, aovs: Vec<Aov>) -> Self {
+        self.aovs = aovs;
+        self
+    }
+
+    /// Print the progress on the terminal.
+    pub const fn with_log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// Stop rendering as soon as `stop` is set.
+    ///
+    /// The tiles that are being rendered at that moment are still finished. Pixels that didn't
+    /// receive any samples yet are black.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Render `root` as seen by `camera` into an image with the resolution of `target`.
+    ///
+    /// The pixels are sent to `samples_tx` whenever they change, e.g. to show them in a
+    /// [`Preview`](crate::ui::Preview), as the unclamped average of their samples so far. The
+    /// receiving end may be dropped if nobody is interested.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a render thread panicked.
+    pub fn render(
+        &self,
+        camera: &Camera,
+        root: &Arc<dyn Hit>,
+        target: &Image,
+        samples_tx: &Sender<((u32, u32), Color)>,
+    ) -> RenderOutput {
+        let job = Job::new(self, camera, root.as_ref(), target);
+
+        if let Some(base_samples) = self.adaptive {
+            job.run_adaptive(base_samples, samples_tx);
+        } else {
+            job.run_threads(None, samples_tx);
+        }
+
+        job.finish()
+    }
+}
+
+/// The state of one render shared by all of its threads.
+struct Job<'a> {
+    renderer: &'a Renderer,
+    camera: &'a Camera,
+    root: &'a dyn Hit,
+    width: u32,
+    tiles: Vec<Tile>,
+    /// Every pass renders each tile once, either with all samples or with a single one.
+    samples_per_pass: u32,
+    work: usize,
+    next_work: AtomicUsize,
+    tiles_done: AtomicUsize,
+    samples_done: AtomicU64,
+    /// Set once the stopping criterion is met.
+    done: AtomicBool,
+    /// Set if adaptive sampling found no more noise to remove.
+    converged: AtomicBool,
+    film: Film,
+    /// How many passes of every tile were added to the film, so they are added in order.
+    passes_added: Mutex<Vec<u32>>,
+    pass_added: Condvar,
+    progress: Mutex<Option<Progress>>,
+    start: Instant,
+}
+
+/// The samples every pixel takes in one round of adaptive sampling, row by row over the image.
+struct Round {
+    /// The index of the first sample of every pixel in this round.
+    first: Vec<u32>,
+    counts: Vec<u32>,
+}
+
+impl<'a> Job<'a> {
+    fn new(renderer: &'a Renderer, camera: &'a Camera, root: &'a dyn Hit, target: &Image) -> Self {
+        let tiles = Tile::split(target.width(), target.height(), TILE_SIZE);
+        let (passes, samples_per_pass) = match renderer.criterion {
+            StoppingCriterion::Samples if renderer.progressive => (camera.samples, 1),
+            StoppingCriterion::Samples => (1, camera.samples),
+            StoppingCriterion::TimeBudget(_) | StoppingCriterion::TargetNoise(_) => (u32::MAX, 1),
+        };
+        let progress_total = match renderer.criterion {
+            StoppingCriterion::Samples => target.pixel_count() * camera.samples as usize,
+            StoppingCriterion::TimeBudget(budget) => {
+                usize::try_from(budget.as_millis()).unwrap_or(usize::MAX)
+            }
+            StoppingCriterion::TargetNoise(_) => NOISE_PROGRESS_STEPS as usize,
+        };
+
+        Self {
+            renderer,
+            camera,
+            root,
+            width: target.width(),
+            work: tiles.len().saturating_mul(passes as usize),
+            passes_added: Mutex::new(vec![0; tiles.len()]),
+            pass_added: Condvar::new(),
+            tiles,
+            samples_per_pass,
+            next_work: AtomicUsize::new(0),
+            tiles_done: AtomicUsize::new(0),
+            samples_done: AtomicU64::new(0),
+            done: AtomicBool::new(false),
+            converged: AtomicBool::new(false),
+            film: Film::new(target.width(), target.height()).with_aovs(!renderer.aovs.is_empty()),
+            progress: Mutex::new(renderer.log.then(|| Progress::new(progress_total))),
+            start: Instant::now(),
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.done.load(Ordering::Relaxed) || self.renderer.stop.load(Ordering::Relaxed)
+    }
+
+    /// Render the base samples and then the adaptive rounds until the budget is used up or the
+    /// stopping criterion is met.
+    fn run_adaptive(&self, base_samples: u32, samples_tx: &Sender<((u32, u32), Color)>) {
+        let pixel_count = self.film.pixel_count();
+        let mut remaining = match self.renderer.criterion {
+            StoppingCriterion::Samples => Some(u64::from(self.camera.samples) * pixel_count as u64),
+            StoppingCriterion::TimeBudget(_) | StoppingCriterion::TargetNoise(_) => None,
+        };
+        let base_samples = match remaining {
+            Some(_) => base_samples.max(2).min(self.camera.samples),
+            None => base_samples.max(2),
+        };
+        let mut counts = vec![base_samples; pixel_count];
+
+        loop {
+            let round = Round {
+                first: self.film.counts(),
+                counts,
+            };
+            self.next_work.store(0, Ordering::Relaxed);
+            self.run_threads(Some(&round), samples_tx);
+
+            if let Some(remaining) = &mut remaining {
+                *remaining =
+                    remaining.saturating_sub(round.counts.iter().copied().map(u64::from).sum());
+            }
+            if self.should_stop() || remaining == Some(0) {
+                break;
+            }
+
+            let budget = remaining
+                .unwrap_or(u64::MAX)
+                .min(pixel_count as u64 * u64::from(base_samples));
+            counts = self.film.allocate(budget, MAX_ROUND_SHARE * base_samples);
+            if counts.iter().all(|&count| count == 0) {
+                self.converged.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    }
+
+    /// Render on all threads until the queue is empty or the render should stop.
+    ///
+    /// Without a `round`, the queue holds all passes over all tiles. Otherwise, it holds every
+    /// tile once with the samples of the round.
+    fn run_threads(&self, round: Option<&Round>, samples_tx: &Sender<((u32, u32), Color)>) {
+        thread::scope(|scope| {
+            for _ in 0..self.renderer.num_threads {
+                let samples_tx = samples_tx.clone();
+                scope.spawn(move || self.run(round, &samples_tx));
+            }
+        });
+    }
+
+    /// Render tiles from the queue until it is empty or the render should stop.
+    fn run(&self, round: Option<&Round>, samples_tx: &Sender<((u32, u32), Color)>) {
+        let work = round.map_or(self.work, |_| self.tiles.len());
+
+        loop {
+            if let StoppingCriterion::TimeBudget(budget) = self.renderer.criterion {
+                if self.start.elapsed() >= budget {
+                    self.done.store(true, Ordering::Relaxed);
+                }
+            }
+
+            let index = self.next_work.fetch_add(1, Ordering::Relaxed);
+            if index >= work || self.should_stop() {
+                break;
+            }
+            let tile_index = index % self.tiles.len();
+            let tile = &self.tiles[tile_index];
+            #[allow(clippy::cast_possible_truncation)]
+            let pass = (index / self.tiles.len()) as u32;
+
+            let (samples, aovs): (Vec<_>, Vec<_>) = tile
+                .pixels()
+                .map(|(x, y)| {
+                    let (first, count) = round.map_or(
+                        (pass * self.samples_per_pass, self.samples_per_pass),
+                        |round| {
+                            let index = (y * self.width + x) as usize;
+                            (round.first[index], round.counts[index])
+                        },
+                    );
+
+                    let mut pixel = PixelSamples::default();
+                    let mut aovs = AovPixel::default();
+                    for sample in first..first + count {
+                        if self.film.has_aovs() {
+                            let mut aov_sample = AovSample::default();
+                            pixel.add(self.camera.trace_sample(
+                                self.root,
+                                x,
+                                y,
+                                sample,
+                                &mut |event| aov_sample.record(&event),
+                            ));
+                            aovs.add(&aov_sample);
+                        } else {
+                            pixel.add(self.camera.sample_pixel(self.root, x, y, sample));
+                        }
+                    }
+                    (pixel, aovs)
+                })
+                .unzip();
+            let taken = samples.iter().map(|pixel| u64::from(pixel.count())).sum();
+
+            // every round takes each tile once, so only the passes need to be kept in order
+            if round.is_none() {
+                self.wait_for_pass(tile_index, pass);
+            }
+            if taken > 0 {
+                self.film.add_tile(tile, &samples);
+                self.film.add_aov_tile(tile, &aovs);
+                for ((x, y), color) in tile.pixels().zip(self.film.averages(tile)) {
+                    let color = self.camera.working_space.to_linear_srgb(color);
+                    let _ = samples_tx.send(((x, y), color));
+                }
+            }
+            if round.is_none() {
+                self.pass_done(tile_index);
+            }
+
+            self.tile_done(taken);
+        }
+    }
+
+    /// Wait until the passes of tile `tile` before `pass` were added to the film.
+    ///
+    /// Floating point sums depend on the order of their terms, so adding passes out of order
+    /// would make the result depend on how the threads were scheduled. Stops waiting if the
+    /// render should stop, since an earlier pass might then never be added.
+    fn wait_for_pass(&self, tile: usize, pass: u32) {
+        let mut passes_added = self.passes_added.lock().expect("pass lock poisoned");
+
+        while passes_added[tile] < pass && !self.should_stop() {
+            passes_added = self
+                .pass_added
+                .wait_timeout(passes_added, STOP_POLL_INTERVAL)
+                .expect("pass lock poisoned")
+                .0;
+        }
+        drop(passes_added);
+    }
+
+    /// Record that the next pass of tile `tile` was added to the film.
+    fn pass_done(&self, tile: usize) {
+        self.passes_added.lock().expect("pass lock poisoned")[tile] += 1;
+        self.pass_added.notify_all();
+    }
+
+    /// Check the stopping criterion and report the progress after a tile with `taken` samples
+    /// was added to the film.
+    fn tile_done(&self, taken: u64) {
+        let tiles_done = self.tiles_done.fetch_add(1, Ordering::Relaxed) + 1;
+        let pass_done = tiles_done.is_multiple_of(self.tiles.len());
+        let samples_done = self.samples_done.fetch_add(taken, Ordering::Relaxed) + taken;
+
+        let progress = match self.renderer.criterion {
+            StoppingCriterion::Samples => Some(usize::try_from(samples_done).unwrap_or(usize::MAX)),
+            StoppingCriterion::TimeBudget(_) => {
+                Some(usize::try_from(self.start.elapsed().as_millis()).unwrap_or(usize::MAX))
+            }
+            StoppingCriterion::TargetNoise(target) => {
+                pass_done.then(|| self.film.noise()).flatten().map(|noise| {
+                    if noise <= target {
+                        self.done.store(true, Ordering::Relaxed);
+                    }
+                    // the noise falls with the square root of the samples
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let progress = ((target / noise).powi(2).min(1.0)
+                        * f64::from(NOISE_PROGRESS_STEPS))
+                        as usize;
+                    progress
+                })
+            }
+        };
+
+        if let Some(done) = progress {
+            if let Some(progress) = self
+                .progress
+                .lock()
+                .expect("progress lock poisoned")
+                .as_mut()
+            {
+                progress.update(done);
+            }
+        }
+    }
+
+    fn finish(self) -> RenderOutput {
+        if let Some(progress) = self
+            .progress
+            .into_inner()
+            .expect("progress lock poisoned")
+            .as_mut()
+        {
+            progress.finish();
+        }
+
+        let reason = if self.renderer.stop.load(Ordering::Relaxed) {
+            StopReason::Stopped
+        } else if self.converged.load(Ordering::Relaxed) {
+            StopReason::Converged
+        } else {
+            match self.renderer.criterion {
+                StoppingCriterion::Samples => StopReason::SamplesTaken(self.camera.samples),
+                StoppingCriterion::TimeBudget(budget) => StopReason::TimeBudget(budget),
+                StoppingCriterion::TargetNoise(target) => StopReason::TargetNoise(target),
+            }
+        };
+        let (samples, min_samples) = self.film.sample_counts();
+        let stats = RenderStats {
+            reason,
+            duration: self.start.elapsed(),
+            samples,
+            min_samples,
+            pixel_count: self.film.pixel_count(),
+            noise: self.film.noise(),
+        };
+
+        RenderOutput {
+            image: self
+                .film
+                .to_image()
+                .map(|color| self.camera.working_space.to_linear_srgb(color)),
+            sample_map: self.film.sample_map(),
+            aovs: self
+                .renderer
+                .aovs
+                .iter()
+                .filter_map(|&aov| {
+                    let image = self.film.aov_image(aov, self.camera.working_space)?;
+                    Some((aov, image))
+                })
+                .collect(),
+            stats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+
+    use approx::assert_abs_diff_eq;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::background::background_color::BackgroundColor;
+    use crate::background::Background;
+    use crate::camera::CameraBuilder;
+    use crate::color::Color;
+    use crate::hit::bvh::flat::FlatBvh;
+    use crate::hit::Hit;
+    use crate::image::Image;
+    use crate::ray::Ray;
+    use crate::render::aov::Aov;
+    use crate::render::stats::StopReason;
+    use crate::scene_file::SceneFile;
+    use crate::vec::Vec3;
+
+    use super::{RenderOutput, Renderer, StoppingCriterion};
+
+    #[test]
+    fn renders_every_pixel_once() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_samples(1)
+            .with_background(BackgroundColor::new(0.2, 0.4, 0.6))
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, samples_rx) = mpsc::channel();
+
+        let RenderOutput { image, stats, .. } =
+            Renderer::new(3).render(&camera, &root, &target, &samples_tx);
+        drop(samples_tx);
+
+        for y in 0..target.height() {
+            for x in 0..target.width() {
+                assert_abs_diff_eq!(image.get_pixel(x, y), Color::new(0.2, 0.4, 0.6));
+            }
+        }
+        assert_eq!(samples_rx.iter().count(), target.pixel_count());
+        assert_eq!(stats.reason, StopReason::SamplesTaken(1));
+        assert_eq!(stats.samples, 800);
+        assert_eq!(stats.min_samples, 1);
+        assert_eq!(stats.noise, None);
+    }
+
+    #[test]
+    fn progressive() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_samples(4)
+            .with_background(BackgroundColor::new(0.2, 0.4, 0.6))
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, samples_rx) = mpsc::channel();
+
+        let result =
+            Renderer::new(3)
+                .with_progressive(true)
+                .render(&camera, &root, &target, &samples_tx);
+        drop(samples_tx);
+
+        assert_abs_diff_eq!(result.image.get_pixel(39, 19), Color::new(0.2, 0.4, 0.6));
+        // the preview receives every pixel once per pass
+        assert_eq!(samples_rx.iter().count(), 4 * target.pixel_count());
+    }
+
+    /// Render the noise scene with `seed` progressively on `threads` threads.
+    fn render_seeded(seed: u64, threads: u32) -> Vec<Color> {
+        let target = Image::with_dimensions(20, 20, Color::black());
+        let scene = SceneFile::load("scenes/noise.ron")
+            .expect("could not load scene")
+            .with_seed(seed);
+        let (root, lights) = scene.build_objects().expect("could not build scene");
+        let camera = scene
+            .camera_builder()
+            .expect("could not build camera")
+            .with_samples(4)
+            .with_lights(lights)
+            .build(&target);
+        let (samples_tx, _) = mpsc::channel();
+
+        let image = Renderer::new(threads)
+            .with_progressive(true)
+            .render(&camera, &root, &target, &samples_tx)
+            .image;
+
+        (0..target.height())
+            .flat_map(|y| (0..target.width()).map(move |x| (x, y)))
+            .map(|(x, y)| image.get_pixel(x, y))
+            .collect()
+    }
+
+    #[test]
+    fn reproducible() {
+        let reference = render_seeded(7, 1);
+
+        assert_eq!(render_seeded(7, 3), reference);
+        assert_ne!(render_seeded(8, 1), reference);
+    }
+
+    #[test]
+    fn aovs() {
+        let target = Image::with_dimensions(16, 16, Color::black());
+        let scene = SceneFile::load("scenes/cornell_box.ron").expect("could not load scene");
+        let (root, lights) = scene.build_objects().expect("could not build scene");
+        let camera = scene
+            .camera_builder()
+            .expect("could not build camera")
+            .with_samples(4)
+            .with_lights(lights)
+            .build(&target);
+        let (samples_tx, _) = mpsc::channel();
+
+        let result = Renderer::new(2).with_aovs(Aov::ALL.to_vec()).render(
+            &camera,
+            &root,
+            &target,
+            &samples_tx,
+        );
+        let aov = |aov: Aov, x, y| {
+            result
+                .aovs
+                .iter()
+                .find(|(other, _)| *other == aov)
+                .map(|(_, image)| image.get_pixel(x, y))
+                .expect("AOV missing")
+        };
+
+        assert_eq!(result.aovs.len(), Aov::ALL.len());
+        for (x, y) in (0..16).flat_map(|y| (0..16).map(move |x| (x, y))) {
+            // the lighting AOVs split the image
+            assert_abs_diff_eq!(
+                aov(Aov::Direct, x, y) + aov(Aov::Indirect, x, y) + aov(Aov::Emission, x, y),
+                result.image.get_pixel(x, y),
+                epsilon = 1e-9
+            );
+        }
+        // the back wall, a white material from the scene
+        assert!(aov(Aov::Depth, 8, 4).r() > 800.0);
+        assert_abs_diff_eq!(aov(Aov::Normal, 8, 4), Color::new(0.0, 0.0, -1.0));
+        assert_abs_diff_eq!(aov(Aov::Albedo, 8, 4), Color::white());
+        assert_abs_diff_eq!(aov(Aov::ObjectId, 8, 4), Color::new(6.0, 6.0, 6.0));
+        assert_abs_diff_eq!(aov(Aov::MaterialId, 8, 4), Color::white());
+        assert_abs_diff_eq!(aov(Aov::Emission, 8, 4), Color::black());
+    }
+
+    #[test]
+    fn stop() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_background(BackgroundColor::white())
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, _) = mpsc::channel();
+
+        let RenderOutput { image, stats, .. } = Renderer::new(3)
+            .with_stop(Arc::new(AtomicBool::new(true)))
+            .render(&camera, &root, &target, &samples_tx);
+
+        assert_abs_diff_eq!(image.get_pixel(0, 0), Color::black());
+        assert_eq!(stats.reason, StopReason::Stopped);
+        assert_eq!(stats.samples, 0);
+    }
+
+    #[test]
+    fn time_budget() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_background(BackgroundColor::white())
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, _) = mpsc::channel();
+
+        let RenderOutput { image, stats, .. } = Renderer::new(2)
+            .with_stopping_criterion(StoppingCriterion::TimeBudget(Duration::from_millis(50)))
+            .render(&camera, &root, &target, &samples_tx);
+
+        assert_abs_diff_eq!(image.get_pixel(39, 19), Color::white());
+        assert_eq!(
+            stats.reason,
+            StopReason::TimeBudget(Duration::from_millis(50))
+        );
+        assert!(stats.duration >= Duration::from_millis(50));
+        assert!(stats.samples > 0);
+    }
+
+    #[test]
+    fn target_noise() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_background(BackgroundColor::new(0.2, 0.4, 0.6))
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, _) = mpsc::channel();
+
+        let stats = Renderer::new(1)
+            .with_stopping_criterion(StoppingCriterion::TargetNoise(0.01))
+            .render(&camera, &root, &target, &samples_tx)
+            .stats;
+
+        // the background has no noise at all, so the first pass that can estimate it is enough
+        assert_eq!(stats.reason, StopReason::TargetNoise(0.01));
+        assert_eq!(stats.noise, Some(0.0));
+        assert_eq!(stats.min_samples, 2);
+        assert_eq!(stats.samples, 2 * 800);
+    }
+
+    /// A background that is white on the left half of the image and noisy on the right half.
+    ///
+    /// The noise is derived from the direction of the ray, so renders are reproducible.
+    struct HalfNoisy;
+
+    impl Background for HalfNoisy {
+        fn background(&self, ray: &Ray) -> Color {
+            if ray.direction.0 < 0.0 {
+                return Color::white();
+            }
+
+            let mut hasher = DefaultHasher::new();
+            for component in [ray.direction.0, ray.direction.1, ray.direction.2] {
+                component.to_bits().hash(&mut hasher);
+            }
+            Color::white() * StdRng::seed_from_u64(hasher.finish()).gen_range(0.0..2.0)
+        }
+    }
+
+    #[test]
+    fn adaptive() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_forward(Vec3(0.0, 0.0, -1.0))
+            .with_samples(16)
+            .with_background(HalfNoisy)
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, _) = mpsc::channel();
+
+        let RenderOutput {
+            image,
+            sample_map,
+            stats,
+            ..
+        } = Renderer::new(2)
+            .with_adaptive(Some(4))
+            .render(&camera, &root, &target, &samples_tx);
+
+        assert_eq!(stats.reason, StopReason::SamplesTaken(16));
+        assert_eq!(stats.samples, 16 * 800);
+        assert_eq!(stats.min_samples, 4);
+        // the flat half only gets the base samples, the noisy half the rest
+        assert_abs_diff_eq!(image.get_pixel(0, 10), Color::white());
+        assert!(sample_map.get_pixel(0, 10).r() < sample_map.get_pixel(39, 10).r());
+    }
+
+    #[test]
+    fn adaptive_converged() {
+        let target = Image::with_dimensions(40, 20, Color::black());
+        let camera = CameraBuilder::default()
+            .with_samples(16)
+            .with_background(BackgroundColor::new(0.2, 0.4, 0.6))
+            .build(&target);
+        let root: Arc<dyn Hit> = Arc::new(FlatBvh::new(Vec::new()));
+        let (samples_tx, _) = mpsc::channel();
+
+        let stats = Renderer::new(2)
+            .with_adaptive(Some(4))
+            .render(&camera, &root, &target, &samples_tx)
+            .stats;
+
+        assert_eq!(stats.reason, StopReason::Converged);
+        assert_eq!(stats.samples, 4 * 800);
+    }
+}
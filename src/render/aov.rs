@@ -0,0 +1,316 @@
+//! Arbitrary output variables: images of properties of the scene besides its color, for
+//! compositing and denoising.
+
+use crate::color::space::WorkingSpace;
+use crate::color::Color;
+use crate::integrator::{LightSource, PathEvent};
+
+/// An arbitrary output variable.
+///
+/// The surface properties come from the surface the camera ray hits first, the lighting ones
+/// split the color of the render by how the light reached the camera. Direct, indirect and
+/// emission add up to the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// The distance from the camera, averaged over the samples that hit a surface.
+    Depth,
+    /// The world space normal facing the camera, averaged over the samples that hit a surface.
+    Normal,
+    /// The albedo of the material.
+    Albedo,
+    /// The texture coordinates in the red and green channels, averaged over the samples that hit
+    /// a surface.
+    Uv,
+    /// The [`ObjectId`](crate::hit::id::ObjectId) of the object, 0 for none.
+    ObjectId,
+    /// The [`MaterialId`](crate::material::id::MaterialId) of the material, 0 for none.
+    MaterialId,
+    /// Light that reached the first surface straight from a light or the background.
+    Direct,
+    /// Light that reached the first surface after bouncing off other surfaces.
+    Indirect,
+    /// Light emitted by the first surface, or the background if there is none.
+    Emission,
+}
+
+/// How the samples of a pixel are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    /// Average over all samples.
+    All,
+    /// Average over the samples that hit a surface.
+    Hits,
+    /// Keep the value of the first sample, since averaging IDs would give meaningless ones.
+    First,
+}
+
+impl Aov {
+    pub const ALL: [Self; 9] = [
+        Self::Depth,
+        Self::Normal,
+        Self::Albedo,
+        Self::Uv,
+        Self::ObjectId,
+        Self::MaterialId,
+        Self::Direct,
+        Self::Indirect,
+        Self::Emission,
+    ];
+
+    /// The name of the AOV, e.g. for its layer in an EXR file.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Depth => "depth",
+            Self::Normal => "normal",
+            Self::Albedo => "albedo",
+            Self::Uv => "uv",
+            Self::ObjectId => "object_id",
+            Self::MaterialId => "material_id",
+            Self::Direct => "direct",
+            Self::Indirect => "indirect",
+            Self::Emission => "emission",
+        }
+    }
+
+    /// Whether the AOV holds colors, which are converted from the working space like the
+    /// rendered image.
+    pub const fn is_color(self) -> bool {
+        matches!(
+            self,
+            Self::Albedo | Self::Direct | Self::Indirect | Self::Emission
+        )
+    }
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+
+    const fn filter(self) -> Filter {
+        match self {
+            Self::Depth | Self::Normal | Self::Uv => Filter::Hits,
+            Self::ObjectId | Self::MaterialId => Filter::First,
+            Self::Albedo | Self::Direct | Self::Indirect | Self::Emission => Filter::All,
+        }
+    }
+}
+
+/// The values of every AOV for one sample, recorded from the events of its path.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AovSample {
+    values: [Color; Aov::ALL.len()],
+    hit: bool,
+}
+
+impl AovSample {
+    pub fn record(&mut self, event: &PathEvent) {
+        match *event {
+            PathEvent::Hit {
+                bounces: 0,
+                ray,
+                hit,
+            } => {
+                let depth = hit.t * ray.direction.len();
+                let ids = [
+                    (Aov::ObjectId, hit.object_id),
+                    (Aov::MaterialId, hit.material.id()),
+                ];
+
+                self.hit = true;
+                self.set(Aov::Depth, Color::new(depth, depth, depth));
+                self.set(Aov::Normal, hit.normal.into());
+                self.set(Aov::Albedo, hit.material.albedo(hit));
+                self.set(Aov::Uv, Color::new(hit.u, hit.v, 0.0));
+                for (aov, id) in ids {
+                    let id = f64::from(id);
+                    self.set(aov, Color::new(id, id, id));
+                }
+            }
+            PathEvent::Light {
+                bounces,
+                source,
+                radiance,
+            } => {
+                let aov = match (bounces, source) {
+                    (0, LightSource::Emission | LightSource::Background) => Aov::Emission,
+                    (0, LightSource::Direct)
+                    | (1, LightSource::Emission | LightSource::Background) => Aov::Direct,
+                    _ => Aov::Indirect,
+                };
+                self.values[aov.index()] += radiance;
+            }
+            PathEvent::Hit { .. } | PathEvent::End { .. } => {}
+        }
+    }
+
+    pub const fn get(&self, aov: Aov) -> Color {
+        self.values[aov.index()]
+    }
+
+    const fn set(&mut self, aov: Aov, value: Color) {
+        self.values[aov.index()] = value;
+    }
+}
+
+/// The running sums of the AOVs of the samples of one pixel.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AovPixel {
+    sums: [Color; Aov::ALL.len()],
+    hits: u32,
+    count: u32,
+}
+
+impl AovPixel {
+    pub fn add(&mut self, sample: &AovSample) {
+        for aov in Aov::ALL {
+            let sum = &mut self.sums[aov.index()];
+            match aov.filter() {
+                Filter::All => *sum += sample.get(aov),
+                Filter::Hits if sample.hit => *sum += sample.get(aov),
+                Filter::First if self.count == 0 => *sum = sample.get(aov),
+                Filter::Hits | Filter::First => {}
+            }
+        }
+        self.hits += u32::from(sample.hit);
+        self.count += 1;
+    }
+
+    /// Add the samples of `other`, which were taken after the ones of this pixel.
+    pub fn merge(&mut self, other: &Self) {
+        for aov in Aov::ALL {
+            let sum = &mut self.sums[aov.index()];
+            match aov.filter() {
+                Filter::All | Filter::Hits => *sum += other.sums[aov.index()],
+                Filter::First if self.count == 0 => *sum = other.sums[aov.index()],
+                Filter::First => {}
+            }
+        }
+        self.hits += other.hits;
+        self.count += other.count;
+    }
+
+    /// The value of `aov` for the pixel, converting colors from `working_space` to linear sRGB.
+    /// Black if no sample contributed to it.
+    pub fn value(&self, aov: Aov, working_space: WorkingSpace) -> Color {
+        let count = match aov.filter() {
+            Filter::All => self.count,
+            Filter::Hits => self.hits,
+            Filter::First => 1,
+        };
+        if count == 0 {
+            return Color::black();
+        }
+
+        let value = self.sums[aov.index()] / f64::from(count);
+        if aov.is_color() {
+            working_space.to_linear_srgb(value)
+        } else {
+            value
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::space::WorkingSpace;
+    use crate::color::Color;
+    use crate::hit::HitResult;
+    use crate::integrator::{LightSource, PathEvent};
+    use crate::material::id::MaterialId;
+    use crate::material::lambertian::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec::Vec3;
+
+    use super::{Aov, AovPixel, AovSample};
+
+    fn light(bounces: u32, source: LightSource, radiance: f64) -> PathEvent<'static> {
+        PathEvent::Light {
+            bounces,
+            source,
+            radiance: Color::new(radiance, radiance, radiance),
+        }
+    }
+
+    #[test]
+    fn records_first_hit() {
+        let ray = Ray {
+            origin: Vec3::zero(),
+            direction: Vec3(0.0, 0.0, -2.0),
+            time: 0.0,
+        };
+        let material = Arc::new(MaterialId::new(
+            Arc::new(Lambertian::colored(Color::new(0.8, 0.4, 0.2))),
+            3,
+        ));
+        let mut hit = HitResult::new(
+            &ray,
+            1.5,
+            0.25,
+            0.75,
+            ray.at(1.5),
+            Vec3(0.0, 0.0, 1.0),
+            material,
+        );
+        hit.object_id = 7;
+
+        let mut sample = AovSample::default();
+        sample.record(&PathEvent::Hit {
+            bounces: 0,
+            ray: &ray,
+            hit: &hit,
+        });
+
+        assert_abs_diff_eq!(sample.get(Aov::Depth), Color::new(3.0, 3.0, 3.0));
+        assert_abs_diff_eq!(sample.get(Aov::Normal), Color::new(0.0, 0.0, 1.0));
+        assert_abs_diff_eq!(sample.get(Aov::Albedo), Color::new(0.8, 0.4, 0.2));
+        assert_abs_diff_eq!(sample.get(Aov::Uv), Color::new(0.25, 0.75, 0.0));
+        assert_abs_diff_eq!(sample.get(Aov::ObjectId), Color::new(7.0, 7.0, 7.0));
+        assert_abs_diff_eq!(sample.get(Aov::MaterialId), Color::new(3.0, 3.0, 3.0));
+    }
+
+    #[test]
+    fn splits_lighting() {
+        let mut sample = AovSample::default();
+        for event in [
+            light(0, LightSource::Emission, 1.0),
+            light(0, LightSource::Direct, 2.0),
+            light(1, LightSource::Emission, 4.0),
+            light(1, LightSource::Direct, 8.0),
+            light(2, LightSource::Background, 16.0),
+        ] {
+            sample.record(&event);
+        }
+
+        assert_abs_diff_eq!(sample.get(Aov::Emission).r(), 1.0);
+        assert_abs_diff_eq!(sample.get(Aov::Direct).r(), 6.0);
+        assert_abs_diff_eq!(sample.get(Aov::Indirect).r(), 24.0);
+    }
+
+    #[test]
+    fn averages_pixels() {
+        let mut first = AovSample {
+            hit: true,
+            ..AovSample::default()
+        };
+        first.set(Aov::Depth, Color::new(2.0, 2.0, 2.0));
+        first.set(Aov::ObjectId, Color::new(1.0, 1.0, 1.0));
+        first.set(Aov::Albedo, Color::white());
+        let mut missed = AovSample::default();
+        missed.set(Aov::Emission, Color::new(0.5, 0.5, 0.5));
+
+        let mut pixel = AovPixel::default();
+        pixel.add(&first);
+        let mut later = AovPixel::default();
+        later.add(&missed);
+        pixel.merge(&later);
+
+        let value = |aov| pixel.value(aov, WorkingSpace::LinearSrgb);
+        assert_abs_diff_eq!(value(Aov::Depth), Color::new(2.0, 2.0, 2.0));
+        assert_abs_diff_eq!(value(Aov::ObjectId), Color::new(1.0, 1.0, 1.0));
+        assert_abs_diff_eq!(value(Aov::Albedo), Color::new(0.5, 0.5, 0.5));
+        assert_abs_diff_eq!(value(Aov::Emission), Color::new(0.25, 0.25, 0.25));
+    }
+}
@@ -0,0 +1,483 @@
+use std::sync::Mutex;
+
+use crate::color::space::WorkingSpace;
+use crate::color::Color;
+use crate::image::Image;
+use crate::render::aov::{Aov, AovPixel};
+use crate::render::tile::Tile;
+
+/// Brightness below which the noise of a pixel is measured relative to this value instead of its
+/// own brightness, so that nearly black pixels don't dominate the estimate.
+const NOISE_FLOOR: f64 = 0.01;
+
+/// The accumulation buffer all render threads write their samples into.
+///
+/// Every pixel keeps a [`PixelSamples`] summary, so the same pixel can receive samples from
+/// multiple threads or passes.
+pub struct Film {
+    width: u32,
+    height: u32,
+    pixels: Mutex<Vec<PixelSamples>>,
+    /// The AOVs of every pixel, if they are rendered.
+    aovs: Option<Mutex<Vec<AovPixel>>>,
+}
+
+/// The running sums of the samples of one pixel.
+///
+/// Besides the average color, they give an estimate of how noisy the average still is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelSamples {
+    sum: Color,
+    luminance_squares: f64,
+    count: u32,
+}
+
+impl Default for PixelSamples {
+    fn default() -> Self {
+        Self {
+            sum: Color::black(),
+            luminance_squares: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl PixelSamples {
+    pub fn add(&mut self, sample: Color) {
+        self.sum += sample;
+        self.luminance_squares += sample.luminance().powi(2);
+        self.count += 1;
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.sum += other.sum;
+        self.luminance_squares += other.luminance_squares;
+        self.count += other.count;
+    }
+
+    pub const fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// The average of the samples, or black if there are none.
+    pub fn average(&self) -> Color {
+        if self.count == 0 {
+            Color::black()
+        } else {
+            self.sum / f64::from(self.count)
+        }
+    }
+
+    /// The sample variance of the luminance of the samples.
+    ///
+    /// Returns `None` for less than two samples, where there is nothing to estimate it from.
+    pub fn variance(&self) -> Option<f64> {
+        (self.count >= 2).then(|| {
+            let count = f64::from(self.count);
+            let mean = self.sum.luminance() / count;
+            let deviations = (count * mean).mul_add(-mean, self.luminance_squares);
+
+            // anything smaller is just the rounding error of the running sums
+            if deviations <= 4.0 * count * f64::EPSILON * self.luminance_squares {
+                0.0
+            } else {
+                deviations / (count - 1.0)
+            }
+        })
+    }
+
+    /// The estimated standard error of the average luminance, relative to the average itself.
+    ///
+    /// This shrinks with the square root of the number of samples, so halving it takes four
+    /// times as many samples.
+    pub fn relative_error(&self) -> Option<f64> {
+        self.variance().map(|variance| {
+            let mean = self.sum.luminance() / f64::from(self.count);
+            (variance / f64::from(self.count)).sqrt() / mean.max(NOISE_FLOOR)
+        })
+    }
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixel_count = width as usize * height as usize;
+
+        Self {
+            width,
+            height,
+            pixels: Mutex::new(vec![PixelSamples::default(); pixel_count]),
+            aovs: None,
+        }
+    }
+
+    /// Also keep the AOVs of every pixel.
+    #[must_use]
+    pub fn with_aovs(mut self, aovs: bool) -> Self {
+        self.aovs = aovs.then(|| Mutex::new(vec![AovPixel::default(); self.pixel_count()]));
+        self
+    }
+
+    pub const fn has_aovs(&self) -> bool {
+        self.aovs.is_some()
+    }
+
+    pub const fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Add the samples for every pixel of `tile`, row by row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile doesn't fit onto the film or another thread panicked while adding its
+    /// samples.
+    pub fn add_tile(&self, tile: &Tile, samples: &[PixelSamples]) {
+        let mut pixels = self.pixels.lock().expect("film lock poisoned");
+
+        for ((x, y), samples) in tile.pixels().zip(samples) {
+            assert!(
+                x < self.width && y < self.height,
+                "tile outside of the film"
+            );
+
+            pixels[(y * self.width + x) as usize].merge(samples);
+        }
+    }
+
+    /// Add the AOVs for every pixel of `tile`, row by row. Does nothing if the film doesn't keep
+    /// AOVs.
+    ///
+    /// The AOVs of a pixel have to be added in the order their samples were taken, since the
+    /// IDs are the ones of its first sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile doesn't fit onto the film or another thread panicked while adding its
+    /// AOVs.
+    pub fn add_aov_tile(&self, tile: &Tile, aovs: &[AovPixel]) {
+        let Some(pixels) = &self.aovs else {
+            return;
+        };
+        let mut pixels = pixels.lock().expect("film lock poisoned");
+
+        for ((x, y), aovs) in tile.pixels().zip(aovs) {
+            assert!(
+                x < self.width && y < self.height,
+                "tile outside of the film"
+            );
+
+            pixels[(y * self.width + x) as usize].merge(aovs);
+        }
+    }
+
+    /// The average of all samples of every pixel of `tile` so far, row by row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn averages(&self, tile: &Tile) -> Vec<Color> {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+
+        tile.pixels()
+            .map(|(x, y)| pixels[(y * self.width + x) as usize].average())
+            .collect()
+    }
+
+    /// The total number of samples taken so far and the smallest number any pixel received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn sample_counts(&self) -> (u64, u32) {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+
+        (
+            pixels.iter().map(|pixel| u64::from(pixel.count)).sum(),
+            pixels.iter().map(|pixel| pixel.count).min().unwrap_or(0),
+        )
+    }
+
+    /// The number of samples of every pixel, row by row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn counts(&self) -> Vec<u32> {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+
+        pixels.iter().map(PixelSamples::count).collect()
+    }
+
+    /// Split `budget` samples between the pixels in proportion to their relative error, row by
+    /// row, giving no pixel more than `max_samples`.
+    ///
+    /// Pixels with less than two samples have no estimate of their error and receive nothing.
+    /// If no pixel has any error left, nothing is handed out at all.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn allocate(&self, budget: u64, max_samples: u32) -> Vec<u32> {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+        let errors = pixels
+            .iter()
+            .map(|pixel| pixel.relative_error().unwrap_or(0.0))
+            .collect::<Vec<_>>();
+        drop(pixels);
+
+        let total = errors.iter().sum::<f64>();
+        if total <= 0.0 {
+            return vec![0; errors.len()];
+        }
+
+        // round the running total instead of every share, so the shares add up to the budget
+        #[allow(clippy::cast_precision_loss)]
+        let budget = budget as f64;
+        let mut share = 0.0;
+        let mut handed_out = 0;
+        errors
+            .iter()
+            .map(|error| {
+                share += error / total * budget;
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let until = share.round() as u64;
+                let samples = until - handed_out;
+                handed_out = until;
+
+                u32::try_from(samples).unwrap_or(u32::MAX).min(max_samples)
+            })
+            .collect()
+    }
+
+    /// The relative error of the pixels, averaged over the whole film.
+    ///
+    /// Returns `None` until every pixel received at least two samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn noise(&self) -> Option<f64> {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+
+        let total = pixels
+            .iter()
+            .map(PixelSamples::relative_error)
+            .sum::<Option<f64>>()?;
+        #[allow(clippy::cast_precision_loss)]
+        let noise = total / pixels.len().max(1) as f64;
+        drop(pixels);
+
+        Some(noise)
+    }
+
+    /// The average of all samples of every pixel.
+    ///
+    /// The colors are not clamped, so highlights keep their full brightness. Pixels without
+    /// samples are black.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn to_image(&self) -> Image {
+        let pixels = self.pixels.lock().expect("film lock poisoned");
+        let mut image = Image::with_dimensions(self.width, self.height, Color::black());
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let average = pixels[(y * self.width + x) as usize].average();
+                image.set_pixel(x, y, average);
+            }
+        }
+        drop(pixels);
+
+        image
+    }
+
+    /// The image of `aov`, with colors converted from `working_space` to linear sRGB, or `None`
+    /// if the film doesn't keep AOVs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its AOVs.
+    pub fn aov_image(&self, aov: Aov, working_space: WorkingSpace) -> Option<Image> {
+        let pixels = self.aovs.as_ref()?.lock().expect("film lock poisoned");
+        let mut image = Image::with_dimensions(self.width, self.height, Color::black());
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = pixels[(y * self.width + x) as usize].value(aov, working_space);
+                image.set_pixel(x, y, value);
+            }
+        }
+        drop(pixels);
+
+        Some(image)
+    }
+
+    /// How many samples every pixel received, as a gray scale image that is white for the
+    /// pixel with the most samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another thread panicked while adding its samples.
+    pub fn sample_map(&self) -> Image {
+        let counts = self.counts();
+        let max = f64::from(counts.iter().copied().max().unwrap_or(0).max(1));
+        let mut image = Image::with_dimensions(self.width, self.height, Color::black());
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = f64::from(counts[(y * self.width + x) as usize]) / max;
+                image.set_pixel(x, y, Color::new(value, value, value));
+            }
+        }
+
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+    use crate::render::tile::Tile;
+
+    use super::{Film, PixelSamples};
+
+    fn samples(colors: &[Color]) -> PixelSamples {
+        let mut samples = PixelSamples::default();
+        for color in colors {
+            samples.add(*color);
+        }
+        samples
+    }
+
+    #[test]
+    fn accumulates_samples() {
+        let film = Film::new(2, 2);
+        let tile = Tile {
+            x: 1,
+            y: 0,
+            width: 1,
+            height: 2,
+        };
+        let gray = |value| Color::new(value, value, value);
+
+        film.add_tile(&tile, &[samples(&[Color::white()]), samples(&[gray(0.2)])]);
+        film.add_tile(
+            &tile,
+            &[samples(&[Color::black(); 3]), samples(&[gray(0.8); 3])],
+        );
+        let image = film.to_image();
+
+        assert_abs_diff_eq!(image.get_pixel(1, 0), Color::new(0.25, 0.25, 0.25));
+        assert_abs_diff_eq!(image.get_pixel(1, 1), Color::new(0.65, 0.65, 0.65));
+        assert_abs_diff_eq!(image.get_pixel(0, 0), Color::black());
+        assert_eq!(film.sample_counts(), (8, 0));
+    }
+
+    #[test]
+    fn keeps_highlights() {
+        let film = Film::new(1, 1);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+
+        film.add_tile(&tile, &[samples(&[Color::new(6.0, 2.0, 0.5)])]);
+        film.add_tile(&tile, &[samples(&[Color::new(2.0, 0.0, 0.5)])]);
+
+        assert_abs_diff_eq!(film.to_image().get_pixel(0, 0), Color::new(4.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn variance() {
+        let gray = |value| Color::new(value, value, value);
+        let pixel = samples(&[gray(0.2), gray(0.4), gray(0.6), gray(0.8)]);
+
+        assert_abs_diff_eq!(pixel.variance().unwrap(), 0.2 / 3.0, epsilon = 1e-12);
+        assert_abs_diff_eq!(
+            pixel.relative_error().unwrap(),
+            (0.2 / 3.0 / 4.0_f64).sqrt() / 0.5,
+            epsilon = 1e-12
+        );
+        assert_eq!(samples(&[gray(0.5)]).variance(), None);
+        assert_eq!(samples(&[gray(0.5); 2]).relative_error(), Some(0.0));
+    }
+
+    #[test]
+    fn noise() {
+        let film = Film::new(1, 2);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 2,
+        };
+
+        film.add_tile(
+            &tile,
+            &[samples(&[Color::white(); 2]), PixelSamples::default()],
+        );
+        assert_eq!(film.noise(), None);
+
+        film.add_tile(
+            &tile,
+            &[
+                PixelSamples::default(),
+                samples(&[Color::black(), Color::white()]),
+            ],
+        );
+        // the second pixel has a standard error of 0.5 at an average of 0.5
+        assert_abs_diff_eq!(film.noise().unwrap(), 0.5, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn allocate() {
+        let film = Film::new(4, 1);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 1,
+        };
+        let gray = |value| Color::new(value, value, value);
+
+        film.add_tile(
+            &tile,
+            &[
+                samples(&[gray(0.5); 4]),
+                samples(&[gray(0.4), gray(0.6), gray(0.4), gray(0.6)]),
+                samples(&[gray(0.2), gray(0.8), gray(0.2), gray(0.8)]),
+                samples(&[gray(0.5)]),
+            ],
+        );
+
+        // the third pixel is three times as noisy as the second one
+        assert_eq!(film.allocate(40, 100), vec![0, 10, 30, 0]);
+        assert_eq!(film.allocate(41, 100).iter().sum::<u32>(), 41);
+        assert_eq!(film.allocate(40, 20), vec![0, 10, 20, 0]);
+
+        let image = film.sample_map();
+        assert_abs_diff_eq!(image.get_pixel(0, 0), Color::white());
+        assert_abs_diff_eq!(image.get_pixel(3, 0), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn allocate_converged() {
+        let film = Film::new(2, 1);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 1,
+        };
+
+        film.add_tile(&tile, &[samples(&[Color::white(); 3]); 2]);
+
+        assert_eq!(film.allocate(100, 100), vec![0, 0]);
+    }
+}
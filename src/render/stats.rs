@@ -0,0 +1,104 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+/// Why a render ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    /// Every pixel received the camera's number of samples.
+    SamplesTaken(u32),
+    /// The time budget was used up.
+    TimeBudget(Duration),
+    /// The estimated noise dropped below the target.
+    TargetNoise(f64),
+    /// Adaptive sampling found no more noise to remove.
+    Converged,
+    /// The render was stopped from the outside.
+    Stopped,
+}
+
+/// What a render achieved, reported once it finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    pub reason: StopReason,
+    pub duration: Duration,
+    /// The number of samples taken for all pixels together.
+    pub samples: u64,
+    /// The number of samples of the pixel that received the fewest.
+    pub min_samples: u32,
+    pub pixel_count: usize,
+    /// The estimated noise of the result, see [`Film::noise`](crate::render::film::Film::noise).
+    pub noise: Option<f64>,
+}
+
+impl RenderStats {
+    #[allow(clippy::cast_precision_loss)]
+    pub fn samples_per_pixel(&self) -> f64 {
+        self.samples as f64 / self.pixel_count.max(1) as f64
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn samples_per_second(&self) -> f64 {
+        self.samples as f64 / self.duration.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl Display for StopReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SamplesTaken(samples) => write!(f, "all {samples} samples per pixel taken"),
+            Self::TimeBudget(budget) => {
+                write!(f, "time budget of {:.1}s used up", budget.as_secs_f64())
+            }
+            Self::TargetNoise(target) => {
+                write!(f, "noise below the target of {:.2}%", target * 100.0)
+            }
+            Self::Converged => write!(f, "every pixel converged"),
+            Self::Stopped => write!(f, "stopped early"),
+        }
+    }
+}
+
+impl Display for RenderStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "render finished: {}", self.reason)?;
+        writeln!(f, "  time: {:.2}s", self.duration.as_secs_f64())?;
+        writeln!(
+            f,
+            "  samples per pixel: {:.1} (at least {})",
+            self.samples_per_pixel(),
+            self.min_samples
+        )?;
+        write!(f, "  samples per second: {:.0}", self.samples_per_second())?;
+        if let Some(noise) = self.noise {
+            write!(f, "\n  estimated noise: {:.2}%", noise * 100.0)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{RenderStats, StopReason};
+
+    #[test]
+    fn display() {
+        let stats = RenderStats {
+            reason: StopReason::TargetNoise(0.05),
+            duration: Duration::from_millis(2500),
+            samples: 1000,
+            min_samples: 8,
+            pixel_count: 100,
+            noise: Some(0.0412),
+        };
+
+        assert_eq!(
+            stats.to_string(),
+            "render finished: noise below the target of 5.00%\n  time: 2.50s\n  \
+             samples per pixel: 10.0 (at least 8)\n  samples per second: 400\n  \
+             estimated noise: 4.12%"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+/// A rectangular part of the image that is rendered at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Cover an image with tiles of at most `size` × `size` pixels, row by row.
+    ///
+    /// The tiles on the right and bottom edges are smaller if the image size isn't a multiple of
+    /// `size`.
+    pub fn split(width: u32, height: u32, size: u32) -> Vec<Self> {
+        let size = size.max(1);
+
+        (0..height)
+            .step_by(size as usize)
+            .flat_map(|y| {
+                (0..width).step_by(size as usize).map(move |x| Self {
+                    x,
+                    y,
+                    width: size.min(width - x),
+                    height: size.min(height - y),
+                })
+            })
+            .collect()
+    }
+
+    pub const fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// The coordinates of all pixels in the tile, row by row.
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let Self {
+            x,
+            y,
+            width,
+            height,
+        } = *self;
+
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Tile;
+
+    #[test]
+    fn split() {
+        let tiles = Tile::split(40, 20, 16);
+
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(
+            tiles[2],
+            Tile {
+                x: 32,
+                y: 0,
+                width: 8,
+                height: 16
+            }
+        );
+
+        let pixels = tiles.iter().flat_map(Tile::pixels).collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 40 * 20);
+        assert_eq!(pixels.iter().collect::<HashSet<_>>().len(), 40 * 20);
+        assert_eq!(tiles.iter().map(Tile::pixel_count).sum::<usize>(), 40 * 20);
+    }
+}
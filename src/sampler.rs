@@ -0,0 +1,287 @@
+//! Choosing the numbers every random decision of a path is made with.
+//!
+//! A [`Sampler`] provides the numbers for every sample of every pixel, split into dimensions:
+//! the first ones decide the position in the pixel, on the lens and in time, and after that
+//! every bounce has its own block of dimensions. The same decision always uses the same
+//! dimension, so samplers that spread their numbers evenly over a dimension and between pairs of
+//! them can make the samples of a pixel cover all of its paths more evenly than independent
+//! random numbers would, and converge faster.
+
+use crate::sampler::independent::Independent;
+
+pub mod halton;
+pub mod independent;
+pub mod sobol;
+pub mod stratified;
+
+/// The position within the pixel, two dimensions.
+const PIXEL_DIMENSION: u32 = 0;
+/// The position on the lens, two dimensions.
+const LENS_DIMENSION: u32 = 2;
+/// The time the ray is sent at.
+const TIME_DIMENSION: u32 = 4;
+/// The first dimension of the first bounce.
+const FIRST_BOUNCE_DIMENSION: u32 = 6;
+/// The number of dimensions of every bounce.
+const BOUNCE_DIMENSIONS: u32 = 8;
+/// Where in its bounce the light sample is, two dimensions.
+const LIGHT_OFFSET: u32 = 0;
+/// Where in its bounce the Russian roulette decision is.
+const ROULETTE_OFFSET: u32 = 2;
+/// Where in its bounce the numbers for sampling the material start.
+const MATERIAL_OFFSET: u32 = 4;
+/// Mixed into the numbers of dimensions past the end of a bounce, which are always independent.
+const OVERFLOW_SALT: u64 = 0x6f76_6572_666c_6f77;
+
+/// Identifies one sample of one pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SampleId {
+    /// Chooses the random numbers of the whole render, so renders with the same seed take the
+    /// same samples.
+    pub seed: u64,
+    pub pixel: (u32, u32),
+    pub index: u32,
+    /// The number of samples the pixel is planned to receive. Samplers may spread the numbers
+    /// evenly over exactly this many samples, but must still work for larger indices.
+    pub count: u32,
+}
+
+impl SampleId {
+    /// A well distributed hash of the sample and `dimension`.
+    fn hash(&self, dimension: u32) -> u64 {
+        hash(&[
+            self.seed,
+            u64::from(self.pixel.0),
+            u64::from(self.pixel.1),
+            u64::from(self.index),
+            u64::from(dimension),
+        ])
+    }
+}
+
+/// Provides the numbers in `[0, 1)` for every dimension of every sample.
+///
+/// Samplers are stateless, so the same sample always gets the same numbers, no matter which
+/// thread takes it or in which order.
+pub trait Sampler: Send + Sync {
+    /// The number for `dimension` of the sample `id`.
+    fn sample_1d(&self, id: &SampleId, dimension: u32) -> f64;
+
+    /// The numbers for `dimension` and the dimension after it of the sample `id`.
+    ///
+    /// Samplers that spread pairs of dimensions more evenly than single ones override this.
+    fn sample_2d(&self, id: &SampleId, dimension: u32) -> (f64, f64) {
+        (
+            self.sample_1d(id, dimension),
+            self.sample_1d(id, dimension + 1),
+        )
+    }
+}
+
+/// The numbers of one sample, taken from a [`Sampler`] one decision at a time while tracing its
+/// path.
+pub struct SampleStream<'a> {
+    sampler: &'a dyn Sampler,
+    id: SampleId,
+    /// The first dimension of the current bounce.
+    bounce: u32,
+    dimension: u32,
+}
+
+impl<'a> SampleStream<'a> {
+    pub fn new(sampler: &'a dyn Sampler, id: SampleId) -> Self {
+        Self {
+            sampler,
+            id,
+            bounce: FIRST_BOUNCE_DIMENSION,
+            dimension: FIRST_BOUNCE_DIMENSION + MATERIAL_OFFSET,
+        }
+    }
+
+    pub const fn id(&self) -> &SampleId {
+        &self.id
+    }
+
+    /// The position within the pixel, from 0 to 1 across it.
+    pub fn pixel(&self) -> (f64, f64) {
+        self.sampler.sample_2d(&self.id, PIXEL_DIMENSION)
+    }
+
+    /// The position on the lens, from 0 to 1 across it.
+    pub fn lens(&self) -> (f64, f64) {
+        self.sampler.sample_2d(&self.id, LENS_DIMENSION)
+    }
+
+    /// The time of the ray, from 0 to 1 over the time the shutter is open.
+    pub fn time(&self) -> f64 {
+        self.sampler.sample_1d(&self.id, TIME_DIMENSION)
+    }
+
+    /// Move on to the dimensions of the bounce after `bounces` bounces.
+    pub const fn start_bounce(&mut self, bounces: u32) {
+        self.bounce =
+            FIRST_BOUNCE_DIMENSION.saturating_add(bounces.saturating_mul(BOUNCE_DIMENSIONS));
+        self.dimension = self.bounce.saturating_add(MATERIAL_OFFSET);
+    }
+
+    /// The numbers for sampling a light in the current bounce.
+    pub fn light(&self) -> (f64, f64) {
+        self.sampler
+            .sample_2d(&self.id, self.bounce.saturating_add(LIGHT_OFFSET))
+    }
+
+    /// The number for deciding whether the path survives Russian roulette in the current bounce.
+    pub fn roulette(&self) -> f64 {
+        self.sampler
+            .sample_1d(&self.id, self.bounce.saturating_add(ROULETTE_OFFSET))
+    }
+
+    /// The next number for sampling the material in the current bounce.
+    ///
+    /// Once the bounce runs out of dimensions, e.g. because a material rejected many samples,
+    /// the numbers are independent random numbers instead, so they don't repeat the ones of the
+    /// next bounce.
+    pub fn next_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension = self.dimension.saturating_add(1);
+
+        if self.overflowed(dimension.saturating_add(1)) {
+            Independent.sample_1d(&self.overflow_id(), dimension)
+        } else {
+            self.sampler.sample_1d(&self.id, dimension)
+        }
+    }
+
+    /// The next two numbers for sampling the material in the current bounce.
+    ///
+    /// They always start at an even dimension, so samplers that spread pairs of dimensions
+    /// evenly get to do so.
+    pub fn next_2d(&mut self) -> (f64, f64) {
+        let dimension = self.dimension.saturating_add(self.dimension % 2);
+        self.dimension = dimension.saturating_add(2);
+
+        if self.overflowed(dimension.saturating_add(2)) {
+            Independent.sample_2d(&self.overflow_id(), dimension)
+        } else {
+            self.sampler.sample_2d(&self.id, dimension)
+        }
+    }
+
+    /// The sample with a different seed for the numbers past the end of a bounce.
+    const fn overflow_id(&self) -> SampleId {
+        SampleId {
+            seed: self.id.seed ^ OVERFLOW_SALT,
+            ..self.id
+        }
+    }
+
+    /// Whether the dimensions up to `end` reach into the next bounce.
+    const fn overflowed(&self, end: u32) -> bool {
+        end > self.bounce.saturating_add(BOUNCE_DIMENSIONS)
+    }
+}
+
+/// Mix `values` into one well distributed 64-bit hash.
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x243f_6a88_85a3_08d3, |hash, &value| {
+        mix(hash ^ mix(value.wrapping_add(0x9e37_79b9_7f4a_7c15)))
+    })
+}
+
+/// The finalizer of `SplitMix64`, which spreads every bit of `x` over the whole result.
+const fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Turn the upper bits of `bits` into a number in `[0, 1)`.
+#[allow(clippy::cast_precision_loss)]
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampler::halton::Halton;
+    use crate::sampler::independent::Independent;
+    use crate::sampler::sobol::Sobol;
+    use crate::sampler::stratified::Stratified;
+
+    use super::{SampleId, SampleStream, Sampler, BOUNCE_DIMENSIONS};
+
+    /// The mean squared error of estimating the area of a quarter disk from the pixel position
+    /// of `count` samples, over many pixels.
+    fn quarter_disk_error(sampler: &dyn Sampler, count: u32) -> f64 {
+        let pixels = 200;
+        let mut error = 0.0;
+
+        for pixel in 0..pixels {
+            let hits = (0..count)
+                .filter(|&index| {
+                    let stream = SampleStream::new(
+                        sampler,
+                        SampleId {
+                            seed: 0,
+                            pixel: (pixel, 0),
+                            index,
+                            count,
+                        },
+                    );
+                    let (x, y) = stream.pixel();
+                    x.mul_add(x, y * y) < 1.0
+                })
+                .count();
+            #[allow(clippy::cast_precision_loss)]
+            let area = hits as f64 / f64::from(count);
+            error += (area - std::f64::consts::FRAC_PI_4).powi(2);
+        }
+
+        error / f64::from(pixels)
+    }
+
+    #[test]
+    fn converges_faster_than_independent_numbers() {
+        let independent = quarter_disk_error(&Independent, 64);
+
+        for (name, error) in [
+            ("stratified", quarter_disk_error(&Stratified, 64)),
+            ("halton", quarter_disk_error(&Halton, 64)),
+            ("sobol", quarter_disk_error(&Sobol, 64)),
+        ] {
+            assert!(
+                error < independent / 4.0,
+                "{name}: {error} vs. {independent}"
+            );
+        }
+    }
+
+    #[test]
+    fn bounces_use_separate_dimensions() {
+        let sampler = Independent;
+        let id = SampleId {
+            seed: 0,
+            pixel: (3, 4),
+            index: 5,
+            count: 16,
+        };
+        let mut stream = SampleStream::new(&sampler, id);
+
+        stream.start_bounce(2);
+        let first = (stream.light(), stream.roulette(), stream.next_2d());
+        stream.start_bounce(3);
+        let second = (stream.light(), stream.roulette(), stream.next_2d());
+        stream.start_bounce(2);
+
+        assert_ne!(first, second);
+        assert_eq!(first, (stream.light(), stream.roulette(), stream.next_2d()));
+
+        // the material numbers never run into the next bounce
+        stream.start_bounce(2);
+        let numbers = (0..BOUNCE_DIMENSIONS)
+            .map(|_| stream.next_1d())
+            .collect::<Vec<_>>();
+        stream.start_bounce(3);
+        assert!(!numbers.contains(&stream.light().0));
+    }
+}
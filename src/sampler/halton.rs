@@ -0,0 +1,140 @@
+use crate::sampler::independent::Independent;
+use crate::sampler::stratified::permutation_element;
+use crate::sampler::{hash, mix, SampleId, Sampler};
+
+/// The bases of the dimensions of the Halton sequence. Dimensions past these are independent
+/// random numbers, as the sequence gets too regular for large bases.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// The Halton sequence, which mirrors the digits of the sample index in a different prime base
+/// for every dimension.
+///
+/// The digits are scrambled randomly per pixel and dimension (Owen scrambling), so neighbouring
+/// pixels don't share their errors and dimensions with large bases, whose first values would
+/// otherwise just count up in equal steps, don't line up with each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Halton;
+
+impl Sampler for Halton {
+    fn sample_1d(&self, id: &SampleId, dimension: u32) -> f64 {
+        let Some(&base) = PRIMES.get(dimension as usize) else {
+            return Independent.sample_1d(id, dimension);
+        };
+        let scramble = hash(&[
+            id.seed,
+            u64::from(id.pixel.0),
+            u64::from(id.pixel.1),
+            u64::from(dimension),
+        ]);
+
+        scrambled_radical_inverse(base, id.index, scramble)
+    }
+}
+
+/// Mirror the digits of `index` in `base` at the decimal point, permuting every digit randomly
+/// depending on `scramble` and all the digits before it.
+///
+/// The leading zeros are scrambled too, down to the precision of the result, so the values fill
+/// their strata instead of sitting at the start of them.
+fn scrambled_radical_inverse(base: u32, mut index: u32, scramble: u64) -> f64 {
+    let base_inverse = 1.0 / f64::from(base);
+    let mut mirrored = 0.0;
+    let mut digit_scale = base_inverse;
+    let mut prefix = 0u64;
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let digits = (f64::from(f64::MANTISSA_DIGITS) / f64::from(base).log2()).ceil() as u32;
+
+    for _ in 0..digits {
+        let digit = index % base;
+        #[allow(clippy::cast_possible_truncation)]
+        let permutation = mix(scramble ^ prefix) as u32;
+        mirrored += f64::from(permutation_element(digit, base, permutation)) * digit_scale;
+
+        prefix = prefix
+            .wrapping_mul(u64::from(base))
+            .wrapping_add(u64::from(digit) + 1);
+        digit_scale *= base_inverse;
+        index /= base;
+    }
+
+    mirrored.min(1.0 - f64::EPSILON / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::sampler::{SampleId, Sampler};
+
+    use super::Halton;
+
+    #[test]
+    fn stratified_in_every_base() {
+        let sampler = Halton;
+
+        for (dimension, base) in [(0, 2), (1, 3), (2, 5)] {
+            let count = base * base;
+            let strata = (0..count)
+                .map(|index| {
+                    let value = sampler.sample_1d(
+                        &SampleId {
+                            seed: 1,
+                            pixel: (2, 3),
+                            index,
+                            count,
+                        },
+                        dimension,
+                    );
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let stratum = (value * f64::from(count)) as u32;
+                    stratum
+                })
+                .collect::<HashSet<_>>();
+
+            assert_eq!(strata.len(), count as usize, "base {base}");
+        }
+    }
+
+    #[test]
+    fn large_bases_are_uncorrelated() {
+        let sampler = Halton;
+        let count = 16;
+        let mut total = 0.0;
+
+        // without scrambling, the first 16 values of bases 17 and 19 are i/17 and i/19
+        for pixel in 0..50 {
+            let (xs, ys): (Vec<_>, Vec<_>) = (0..count)
+                .map(|index| {
+                    let id = SampleId {
+                        seed: 1,
+                        pixel: (pixel, 0),
+                        index,
+                        count,
+                    };
+                    (sampler.sample_1d(&id, 6), sampler.sample_1d(&id, 7))
+                })
+                .unzip();
+            let mean = |values: &[f64]| values.iter().sum::<f64>() / f64::from(count);
+            let (mean_x, mean_y) = (mean(&xs), mean(&ys));
+            let covariance = xs
+                .iter()
+                .zip(&ys)
+                .map(|(x, y)| (x - mean_x) * (y - mean_y))
+                .sum::<f64>();
+            let spread = |values: &[f64], mean: f64| {
+                values
+                    .iter()
+                    .map(|v| (v - mean).powi(2))
+                    .sum::<f64>()
+                    .sqrt()
+            };
+
+            total += (covariance / (spread(&xs, mean_x) * spread(&ys, mean_y))).abs();
+        }
+
+        assert!(total / 50.0 < 0.4, "{}", total / 50.0);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::sampler::{to_unit, SampleId, Sampler};
+
+/// Independent random numbers for every dimension of every sample.
+///
+/// This is what the other samplers are measured against: it converges the slowest, but it has
+/// no structure that could show up as patterns in the image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Independent;
+
+impl Sampler for Independent {
+    fn sample_1d(&self, id: &SampleId, dimension: u32) -> f64 {
+        to_unit(id.hash(dimension))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::sampler::{SampleId, Sampler};
+
+    use super::Independent;
+
+    #[test]
+    fn uniform() {
+        let sampler = Independent;
+        let values = (0..10_000)
+            .map(|index| {
+                sampler.sample_1d(
+                    &SampleId {
+                        seed: 7,
+                        pixel: (1, 2),
+                        index,
+                        count: 16,
+                    },
+                    3,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert!(values.iter().all(|value| (0.0..1.0).contains(value)));
+        assert_abs_diff_eq!(values.iter().sum::<f64>() / 10_000.0, 0.5, epsilon = 0.01);
+        let below_tenth = values.iter().filter(|&&value| value < 0.1).count();
+        assert!((900..1100).contains(&below_tenth));
+    }
+}
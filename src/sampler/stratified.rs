@@ -0,0 +1,220 @@
+use crate::sampler::{hash, to_unit, SampleId, Sampler};
+
+/// Splits every dimension into as many strata as the pixel has samples and puts one sample into
+/// each, in a different random order for every dimension.
+///
+/// Pairs of dimensions are split into cells of equal area: `floor(sqrt(samples))` rows, with the
+/// samples spread over the rows as evenly as possible. Rows with one more sample are taller, so
+/// this works for any number of samples, not just square ones.
+///
+/// Sample indices past the number of samples start over with new random orders, so every run of
+/// `count` consecutive samples starting at a multiple of it is stratified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stratified;
+
+impl Sampler for Stratified {
+    fn sample_1d(&self, id: &SampleId, dimension: u32) -> f64 {
+        let count = f64::from(id.count.max(1));
+        let stratum = f64::from(stratum(id, dimension));
+
+        jitter(
+            to_unit(id.hash(dimension)),
+            (stratum / count, (stratum + 1.0) / count),
+        )
+    }
+
+    fn sample_2d(&self, id: &SampleId, dimension: u32) -> (f64, f64) {
+        let (x_range, y_range) = cell(id.count.max(1), stratum(id, dimension));
+
+        (
+            jitter(to_unit(id.hash(dimension)), x_range),
+            jitter(to_unit(id.hash(dimension + 1)), y_range),
+        )
+    }
+}
+
+/// Move `u` in `[0, 1)` into `[min, max)`, staying below 1 when rounding would reach the end of
+/// the last stratum.
+fn jitter(u: f64, (min, max): (f64, f64)) -> f64 {
+    u.mul_add(max - min, min).min(1.0 - f64::EPSILON / 2.0)
+}
+
+/// The stratum of the sample `id` in `dimension`, out of `id.count`.
+fn stratum(id: &SampleId, dimension: u32) -> u32 {
+    let count = id.count.max(1);
+    let order = hash(&[
+        id.seed,
+        u64::from(id.pixel.0),
+        u64::from(id.pixel.1),
+        u64::from(id.index / count),
+        u64::from(dimension),
+    ]);
+
+    #[allow(clippy::cast_possible_truncation)]
+    permutation_element(id.index % count, count, order as u32)
+}
+
+/// The horizontal and vertical range of cell `index` when the unit square is split into
+/// `samples` cells of equal area.
+pub(crate) fn cell(samples: u32, index: u32) -> ((f64, f64), (f64, f64)) {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let rows = (f64::from(samples).sqrt() as u32).max(1);
+    let per_row = samples / rows;
+    // the first rows take the remaining samples, one each
+    let longer_rows = samples % rows;
+    let in_longer_rows = longer_rows * (per_row + 1);
+
+    let (row, column, columns) = if index < in_longer_rows {
+        (index / (per_row + 1), index % (per_row + 1), per_row + 1)
+    } else {
+        let index = index - in_longer_rows;
+        (longer_rows + index / per_row, index % per_row, per_row)
+    };
+    let before_row = row * per_row + row.min(longer_rows);
+    let samples = f64::from(samples);
+
+    (
+        (
+            f64::from(column) / f64::from(columns),
+            f64::from(column + 1) / f64::from(columns),
+        ),
+        (
+            f64::from(before_row) / samples,
+            f64::from(before_row + columns) / samples,
+        ),
+    )
+}
+
+/// Element `index` of a random permutation of `0..length` chosen by `seed`.
+///
+/// Kensler's hashed permutation from "Correlated Multi-Jittered Sampling", which doesn't need
+/// to store the permutation.
+pub(super) const fn permutation_element(mut index: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170_893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929_eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935_fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dc_b303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e50_1cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860_a3df);
+        index &= mask;
+        index ^= index >> 5;
+
+        if index < length {
+            return (index + seed) % length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use approx::assert_abs_diff_eq;
+
+    use crate::sampler::{SampleId, Sampler};
+
+    use super::{cell, jitter, permutation_element, Stratified};
+
+    #[test]
+    fn cells_cover_the_square() {
+        for samples in 1..=50 {
+            let cells = (0..samples)
+                .map(|index| cell(samples, index))
+                .collect::<Vec<_>>();
+
+            for ((x_min, x_max), (y_min, y_max)) in &cells {
+                assert_abs_diff_eq!(
+                    (x_max - x_min) * (y_max - y_min),
+                    1.0 / f64::from(samples),
+                    epsilon = 1e-12
+                );
+            }
+            // every point lies in exactly one cell
+            for i in 0..20 {
+                for j in 0..20 {
+                    let (x, y) = ((f64::from(i) + 0.5) / 20.0, (f64::from(j) + 0.5) / 20.0);
+                    let containing = cells
+                        .iter()
+                        .filter(|((x_min, x_max), (y_min, y_max))| {
+                            (*x_min..*x_max).contains(&x) && (*y_min..*y_max).contains(&y)
+                        })
+                        .count();
+                    assert_eq!(containing, 1, "{samples} samples at ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stays_below_one() {
+        let largest = 1.0 - f64::EPSILON / 2.0;
+
+        assert!(largest.mul_add(0.1, 0.9) >= 1.0);
+        assert!(jitter(largest, (0.9, 1.0)) < 1.0);
+        assert_abs_diff_eq!(jitter(0.5, (0.2, 0.4)), 0.3);
+    }
+
+    #[test]
+    fn permutation() {
+        for length in [1, 2, 7, 16, 100] {
+            let elements = (0..length)
+                .map(|index| permutation_element(index, length, 0x1234_5678))
+                .collect::<HashSet<_>>();
+
+            assert_eq!(elements, (0..length).collect());
+        }
+    }
+
+    #[test]
+    fn one_sample_per_stratum() {
+        let sampler = Stratified;
+        let id = |index| SampleId {
+            seed: 3,
+            pixel: (5, 6),
+            index,
+            count: 10,
+        };
+
+        for start in [0, 10, 20] {
+            let strata = (start..start + 10)
+                .map(|index| {
+                    let value = sampler.sample_1d(&id(index), 7);
+                    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                    let stratum = (value * 10.0) as u32;
+                    stratum
+                })
+                .collect::<HashSet<_>>();
+            assert_eq!(strata.len(), 10);
+        }
+
+        let points = (0..10)
+            .map(|index| sampler.sample_2d(&id(index), 0))
+            .collect::<Vec<_>>();
+        for index in 0..10 {
+            let ((x_min, x_max), (y_min, y_max)) = cell(10, index);
+            let inside = points
+                .iter()
+                .filter(|(x, y)| (x_min..x_max).contains(x) && (y_min..y_max).contains(y))
+                .count();
+            assert_eq!(inside, 1);
+        }
+    }
+}
@@ -0,0 +1,892 @@
+//! Scenes described in RON files.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ron::extensions::Extensions;
+use ron::Options;
+use serde::Deserialize;
+
+use crate::background::background_color::BackgroundColor;
+use crate::background::hdri::Hdri;
+use crate::camera::{Camera, CameraBuilder};
+use crate::color::space::{ColorSpace, WorkingSpace};
+use crate::color::Color;
+use crate::hit::bvh::flat::FlatBvh;
+use crate::hit::id::ObjectId;
+use crate::hit::lights::Lights;
+use crate::hit::mesh::{MeshBuilder, MeshError};
+use crate::hit::quad::Quad;
+use crate::hit::r#box;
+use crate::hit::sphere::SphereBuilder;
+use crate::hit::transform::{RotationY, Translation};
+use crate::hit::triangle::Triangle;
+use crate::hit::Hit;
+use crate::image::{Image, ImageError};
+use crate::material::dielectric::Dielectric;
+use crate::material::id::MaterialId;
+use crate::material::lambertian::Lambertian;
+use crate::material::light::DiffuseLight;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::obj_file::{ObjError, ObjFile};
+use crate::texture::checker::Checker;
+use crate::texture::image::ImageTexture;
+use crate::texture::noise::{Perlin, TurbulentPerlin};
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+
+type Triple = (f64, f64, f64);
+type Pair = (f64, f64);
+
+#[derive(Debug)]
+pub enum SceneError {
+    IOError(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    UnknownMaterial {
+        name: String,
+        line: Option<usize>,
+    },
+    Mesh(MeshError),
+    Image {
+        path: PathBuf,
+        error: ImageError,
+    },
+    Obj {
+        path: PathBuf,
+        error: ObjError,
+    },
+}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        Self::IOError(error)
+    }
+}
+
+impl From<MeshError> for SceneError {
+    fn from(error: MeshError) -> Self {
+        Self::Mesh(error)
+    }
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IOError(error) => write!(f, "could not read scene: {error}"),
+            Self::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+            Self::UnknownMaterial {
+                name,
+                line: Some(line),
+            } => write!(f, "line {line}: unknown material `{name}`"),
+            Self::UnknownMaterial { name, line: None } => {
+                write!(f, "unknown material `{name}`")
+            }
+            Self::Mesh(error) => write!(f, "invalid mesh: {error}"),
+            Self::Image { path, error } => write!(f, "{}: {error}", path.display()),
+            Self::Obj { path, error } => write!(f, "{}: {error}", path.display()),
+        }
+    }
+}
+
+/// A scene loaded from a RON scene description.
+///
+/// Relative paths to images inside the description are resolved relative to the directory of the
+/// scene file.
+pub struct SceneFile {
+    description: SceneDescription,
+    source: String,
+    directory: PathBuf,
+    /// The number of noise textures built so far, so each of them gets its own seed.
+    noise_textures: Cell<u64>,
+    /// The number of materials built so far, so each of them gets its own ID.
+    material_ids: Cell<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDescription>,
+    objects: Vec<ObjectDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    focus_distance: f64,
+    defocus_angle: f64,
+    fov: f64,
+    #[serde(default)]
+    position: Option<Triple>,
+    #[serde(default)]
+    forward: Option<Triple>,
+    #[serde(default)]
+    up: Option<Triple>,
+    #[serde(default)]
+    look_at: Option<Triple>,
+    #[serde(default)]
+    samples: Option<u32>,
+    #[serde(default)]
+    max_bounces: Option<u32>,
+    #[serde(default)]
+    roulette_depth: Option<u32>,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    working_space: Option<WorkingSpaceDescription>,
+    #[serde(default)]
+    background: Option<BackgroundDescription>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum WorkingSpaceDescription {
+    LinearSrgb,
+    AcesCg,
+}
+
+#[derive(Deserialize)]
+enum BackgroundDescription {
+    Color(Triple),
+    Hdri {
+        path: PathBuf,
+        strength: f64,
+        rotation: f64,
+    },
+}
+
+#[derive(Deserialize)]
+enum ObjectDescription {
+    Sphere {
+        center: Triple,
+        radius: f64,
+        material: MaterialDescription,
+        #[serde(default)]
+        movement: Option<Triple>,
+    },
+    Quad {
+        origin: Triple,
+        u: Triple,
+        v: Triple,
+        material: MaterialDescription,
+    },
+    Box {
+        a: Triple,
+        b: Triple,
+        material: MaterialDescription,
+    },
+    Triangle {
+        vertices: (Triple, Triple, Triple),
+        material: MaterialDescription,
+        #[serde(default)]
+        normals: Option<(Triple, Triple, Triple)>,
+        #[serde(default)]
+        uvs: Option<(Pair, Pair, Pair)>,
+    },
+    Obj {
+        path: PathBuf,
+        #[serde(default)]
+        material: Option<MaterialDescription>,
+    },
+    Mesh {
+        positions: Vec<Triple>,
+        faces: Vec<(usize, usize, usize)>,
+        material: MaterialDescription,
+        #[serde(default)]
+        normals: Option<Vec<Triple>>,
+        #[serde(default)]
+        uvs: Option<Vec<Pair>>,
+    },
+    Translation {
+        offset: Triple,
+        object: Box<Self>,
+    },
+    RotationY {
+        angle: f64,
+        object: Box<Self>,
+    },
+    Group(Vec<Self>),
+}
+
+#[derive(Deserialize)]
+enum MaterialDescription {
+    Named(String),
+    Lambertian { texture: TextureDescription },
+    Metal { albedo: Triple, fuzz: f64 },
+    Dielectric { refraction_index: f64 },
+    DiffuseLight { emission: TextureDescription },
+}
+
+#[derive(Deserialize)]
+enum TextureDescription {
+    Solid(Triple),
+    Checker {
+        scale: f64,
+        even: Box<Self>,
+        odd: Box<Self>,
+    },
+    /// A color image, decoded from sRGB unless it stores floating point values.
+    Image(PathBuf),
+    /// An image of values that aren't colors, used exactly as they are stored.
+    DataImage(PathBuf),
+    Perlin {
+        scale: f64,
+    },
+    TurbulentPerlin {
+        scale: f64,
+        turbulence: u32,
+    },
+}
+
+impl SceneFile {
+    /// Read and parse a scene file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, can't be parsed or refers to materials that
+    /// are not defined.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let source = fs::read_to_string(&path)?;
+        let directory = path
+            .as_ref()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        Self::parse(source, directory)
+    }
+
+    /// Parse a scene description, resolving relative paths inside of it against `directory`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source can't be parsed or refers to materials that are not
+    /// defined.
+    pub fn parse(source: String, directory: PathBuf) -> Result<Self, SceneError> {
+        let description = Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str::<SceneDescription>(&source)
+            .map_err(|error| SceneError::Parse {
+                line: error.position.line,
+                column: error.position.col,
+                message: error.code.to_string(),
+            })?;
+        let scene = Self {
+            description,
+            source,
+            directory,
+            noise_textures: Cell::new(0),
+            material_ids: Cell::new(0),
+        };
+        scene.check_materials()?;
+
+        Ok(scene)
+    }
+
+    /// Choose the random numbers of the render and the patterns of the noise textures with
+    /// `seed`, instead of the seed from the scene description.
+    #[must_use]
+    pub const fn with_seed(mut self, seed: u64) -> Self {
+        self.description.camera.seed = Some(seed);
+        self
+    }
+
+    /// The seed of the scene, 0 unless the scene description or [`SceneFile::with_seed`] chose
+    /// another one.
+    pub fn seed(&self) -> u64 {
+        self.description.camera.seed.unwrap_or(0)
+    }
+
+    /// The color space the scene is rendered in. Colors in the scene description are always
+    /// linear sRGB.
+    pub const fn working_space(&self) -> WorkingSpace {
+        match self.description.camera.working_space {
+            None | Some(WorkingSpaceDescription::LinearSrgb) => WorkingSpace::LinearSrgb,
+            Some(WorkingSpaceDescription::AcesCg) => WorkingSpace::AcesCg,
+        }
+    }
+
+    /// The camera settings from the scene description.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the background image can't be loaded.
+    pub fn camera_builder(&self) -> Result<CameraBuilder, SceneError> {
+        let camera = &self.description.camera;
+        let mut builder =
+            CameraBuilder::new(camera.focus_distance, camera.defocus_angle, camera.fov);
+
+        if let Some(position) = camera.position {
+            builder = builder.with_position(position.into());
+        }
+        if let Some(forward) = camera.forward {
+            builder = builder.with_forward(forward.into());
+        }
+        if let Some(up) = camera.up {
+            builder = builder.with_up(up.into());
+        }
+        if let Some(look_at) = camera.look_at {
+            builder = builder.look_at(look_at.into());
+        }
+        if let Some(samples) = camera.samples {
+            builder = builder.with_samples(samples);
+        }
+        if let Some(max_bounces) = camera.max_bounces {
+            builder = builder.with_max_bounces(max_bounces);
+        }
+        if let Some(roulette_depth) = camera.roulette_depth {
+            builder = builder.with_roulette_depth(roulette_depth);
+        }
+        builder = builder
+            .with_seed(self.seed())
+            .with_working_space(self.working_space());
+        match &camera.background {
+            Some(BackgroundDescription::Color(color)) => {
+                builder = builder.with_background(BackgroundColor(self.color(*color)));
+            }
+            Some(BackgroundDescription::Hdri {
+                path,
+                strength,
+                rotation,
+            }) => {
+                let path = self.resolve(path);
+                let hdri = Hdri::load(&path, *strength, *rotation, self.working_space())
+                    .map_err(|error| SceneError::Image { path, error })?;
+                builder = builder.with_background(hdri);
+            }
+            None => {}
+        }
+
+        Ok(builder)
+    }
+
+    /// Build the camera and the scene hierarchy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object in the scene is invalid or an image or model can't be loaded.
+    pub fn build(&self, target: &Image) -> Result<(Camera, Arc<dyn Hit>), SceneError> {
+        let (root, lights) = self.build_objects()?;
+
+        Ok((
+            self.camera_builder()?.with_lights(lights).build(target),
+            root,
+        ))
+    }
+
+    /// Build the scene hierarchy without a camera, along with its lights.
+    ///
+    /// Spheres and quads with a diffuse light material are registered as lights, so they can be
+    /// sampled directly. The objects get IDs counting from 1 in the order they appear in, and so
+    /// do the materials, starting with the named ones sorted by name. Noise textures get their
+    /// patterns from the seed of the scene, in the order they appear in, so building the same
+    /// scene with the same seed gives the same objects.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object in the scene is invalid or an image or model can't be loaded.
+    pub fn build_objects(&self) -> Result<(Arc<dyn Hit>, Lights), SceneError> {
+        self.noise_textures.set(0);
+        self.material_ids.set(0);
+        let materials = self
+            .description
+            .materials
+            .iter()
+            .map(|(name, material)| {
+                Ok((
+                    name.as_str(),
+                    self.build_material(material, &BTreeMap::new())?,
+                ))
+            })
+            .collect::<Result<BTreeMap<_, _>, SceneError>>()?;
+
+        let mut lights = Vec::new();
+        let root = Arc::new(FlatBvh::new(
+            self.description
+                .objects
+                .iter()
+                .zip(1..)
+                .map(|(object, id)| {
+                    let object = self.build_object(object, &materials, &mut lights)?;
+                    Ok(Arc::new(ObjectId::new(object, id)) as Arc<dyn Hit>)
+                })
+                .collect::<Result<_, SceneError>>()?,
+        ));
+
+        Ok((root, Lights::new(lights)))
+    }
+
+    fn build_object(
+        &self,
+        description: &ObjectDescription,
+        materials: &BTreeMap<&str, Arc<dyn Material>>,
+        lights: &mut Vec<Arc<dyn Hit>>,
+    ) -> Result<Arc<dyn Hit>, SceneError> {
+        let object: Arc<dyn Hit> = match description {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+                movement,
+            } => {
+                let mut builder = SphereBuilder::new(
+                    (*center).into(),
+                    *radius,
+                    self.build_material(material, materials)?,
+                );
+                if let Some(movement) = movement {
+                    builder = builder.with_movement((*movement).into());
+                }
+
+                Arc::new(builder.build())
+            }
+            ObjectDescription::Quad {
+                origin,
+                u,
+                v,
+                material,
+            } => Arc::new(Quad::new(
+                (*origin).into(),
+                (*u).into(),
+                (*v).into(),
+                self.build_material(material, materials)?,
+            )),
+            ObjectDescription::Box { a, b, material } => Arc::new(r#box::Box::new(
+                (*a).into(),
+                (*b).into(),
+                self.build_material(material, materials)?,
+            )),
+            ObjectDescription::Triangle {
+                vertices,
+                material,
+                normals,
+                uvs,
+            } => Arc::new(triangle(
+                vertices,
+                normals.as_ref(),
+                uvs.as_ref(),
+                self.build_material(material, materials)?,
+            )),
+            ObjectDescription::Mesh {
+                positions,
+                faces,
+                material,
+                normals,
+                uvs,
+            } => Arc::new(
+                mesh_builder(
+                    positions,
+                    faces,
+                    normals.as_deref(),
+                    uvs.as_deref(),
+                    self.build_material(material, materials)?,
+                )
+                .build()?,
+            ),
+            ObjectDescription::Obj { path, material } => {
+                self.build_obj(path, material.as_ref(), materials)?
+            }
+            ObjectDescription::Translation { offset, object } => {
+                self.build_transformed(object, materials, lights, |object| {
+                    Arc::new(Translation::new(object, (*offset).into()))
+                })?
+            }
+            ObjectDescription::RotationY { angle, object } => {
+                self.build_transformed(object, materials, lights, |object| {
+                    Arc::new(RotationY::new(object, *angle))
+                })?
+            }
+            ObjectDescription::Group(objects) => Arc::new(FlatBvh::new(
+                objects
+                    .iter()
+                    .map(|object| self.build_object(object, materials, lights))
+                    .collect::<Result<_, _>>()?,
+            )),
+        };
+
+        if let ObjectDescription::Sphere { material, .. }
+        | ObjectDescription::Quad { material, .. } = description
+        {
+            if self.is_light(material) {
+                lights.push(object.clone());
+            }
+        }
+
+        Ok(object)
+    }
+
+    /// Build an object and apply a transformation to it and to the lights inside of it.
+    fn build_transformed<F>(
+        &self,
+        object: &ObjectDescription,
+        materials: &BTreeMap<&str, Arc<dyn Material>>,
+        lights: &mut Vec<Arc<dyn Hit>>,
+        transform: F,
+    ) -> Result<Arc<dyn Hit>, SceneError>
+    where
+        F: Fn(Arc<dyn Hit>) -> Arc<dyn Hit>,
+    {
+        let mut object_lights = Vec::new();
+        let object = self.build_object(object, materials, &mut object_lights)?;
+        lights.extend(object_lights.into_iter().map(&transform));
+
+        Ok(transform(object))
+    }
+
+    fn is_light(&self, material: &MaterialDescription) -> bool {
+        match material {
+            MaterialDescription::Named(name) => self
+                .description
+                .materials
+                .get(name)
+                .is_some_and(|material| self.is_light(material)),
+            MaterialDescription::DiffuseLight { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn build_obj(
+        &self,
+        path: &Path,
+        material: Option<&MaterialDescription>,
+        materials: &BTreeMap<&str, Arc<dyn Material>>,
+    ) -> Result<Arc<dyn Hit>, SceneError> {
+        let material = material
+            .map(|material| self.build_material(material, materials))
+            .transpose()?;
+        let path = self.resolve(path);
+        let meshes = ObjFile::load(&path)
+            .and_then(|obj| obj.with_working_space(self.working_space()).build(material))
+            .map_err(|error| SceneError::Obj { path, error })?;
+
+        Ok(Arc::new(FlatBvh::new(
+            meshes
+                .into_iter()
+                .map(|mesh| Arc::new(mesh) as Arc<dyn Hit>)
+                .collect(),
+        )))
+    }
+
+    fn build_material(
+        &self,
+        material: &MaterialDescription,
+        materials: &BTreeMap<&str, Arc<dyn Material>>,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        let material: Arc<dyn Material> = match material {
+            // names are checked when parsing the scene
+            MaterialDescription::Named(name) => return Ok(materials[name.as_str()].clone()),
+            MaterialDescription::Lambertian { texture } => Arc::new(Lambertian {
+                texture: self.build_texture(texture)?,
+            }),
+            MaterialDescription::Metal { albedo, fuzz } => Arc::new(Metal {
+                albedo: self.color(*albedo),
+                fuzz: *fuzz,
+            }),
+            MaterialDescription::Dielectric { refraction_index } => Arc::new(Dielectric {
+                refraction_index: *refraction_index,
+            }),
+            MaterialDescription::DiffuseLight { emission } => Arc::new(DiffuseLight {
+                emission: self.build_texture(emission)?,
+            }),
+        };
+
+        Ok(Arc::new(MaterialId::new(material, self.next_material_id())))
+    }
+
+    fn build_texture(&self, texture: &TextureDescription) -> Result<Arc<dyn Texture>, SceneError> {
+        Ok(match texture {
+            TextureDescription::Solid(color) => Arc::new(SolidColor(self.color(*color))),
+            TextureDescription::Checker { scale, even, odd } => Arc::new(Checker::new(
+                *scale,
+                self.build_texture(even)?,
+                self.build_texture(odd)?,
+            )),
+            TextureDescription::Image(path) => {
+                let path = self.resolve(path);
+                let color_space = ColorSpace::for_file(&path);
+
+                Arc::new(self.load_image_texture(path, color_space)?)
+            }
+            TextureDescription::DataImage(path) => {
+                Arc::new(self.load_image_texture(self.resolve(path), ColorSpace::Data)?)
+            }
+            TextureDescription::Perlin { scale } => {
+                Arc::new(Perlin::new(*scale, self.next_noise_seed()))
+            }
+            TextureDescription::TurbulentPerlin { scale, turbulence } => Arc::new(
+                TurbulentPerlin::new(*scale, *turbulence, self.next_noise_seed()),
+            ),
+        })
+    }
+
+    /// The ID of the next material, counting from 1.
+    fn next_material_id(&self) -> u32 {
+        let id = self.material_ids.get() + 1;
+        self.material_ids.set(id);
+
+        id
+    }
+
+    /// A seed for the next noise texture, derived from the seed of the scene.
+    fn next_noise_seed(&self) -> u64 {
+        let index = self.noise_textures.get();
+        self.noise_textures.set(index + 1);
+
+        self.seed().wrapping_add(index)
+    }
+
+    fn load_image_texture(
+        &self,
+        path: PathBuf,
+        color_space: ColorSpace,
+    ) -> Result<ImageTexture, SceneError> {
+        ImageTexture::load(&path, color_space, self.working_space())
+            .map_err(|error| SceneError::Image { path, error })
+    }
+
+    /// Convert a linear sRGB color from the scene description into the working space.
+    fn color(&self, color: Triple) -> Color {
+        self.working_space().from_linear_srgb(color.into())
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.directory.join(path)
+    }
+
+    fn check_materials(&self) -> Result<(), SceneError> {
+        let mut references = Vec::new();
+        for material in self.description.materials.values() {
+            // named materials can't refer to other named materials
+            if let MaterialDescription::Named(name) = material {
+                return Err(self.unknown_material(name));
+            }
+        }
+        for object in &self.description.objects {
+            object.material_references(&mut references);
+        }
+
+        references
+            .into_iter()
+            .find(|name| !self.description.materials.contains_key(*name))
+            .map_or(Ok(()), |name| Err(self.unknown_material(name)))
+    }
+
+    fn unknown_material(&self, name: &str) -> SceneError {
+        let quoted = format!("\"{name}\"");
+        let line = self
+            .source
+            .lines()
+            .position(|line| line.contains("Named") && line.contains(&quoted))
+            .map(|index| index + 1);
+
+        SceneError::UnknownMaterial {
+            name: name.to_string(),
+            line,
+        }
+    }
+}
+
+fn triangle(
+    vertices: &(Triple, Triple, Triple),
+    normals: Option<&(Triple, Triple, Triple)>,
+    uvs: Option<&(Pair, Pair, Pair)>,
+    material: Arc<dyn Material>,
+) -> Triangle {
+    let mut triangle = Triangle::new(
+        vertices.0.into(),
+        vertices.1.into(),
+        vertices.2.into(),
+        material,
+    );
+    if let Some(normals) = normals {
+        triangle = triangle.with_normals([normals.0.into(), normals.1.into(), normals.2.into()]);
+    }
+    if let Some(uvs) = uvs {
+        triangle = triangle.with_uvs([uvs.0, uvs.1, uvs.2]);
+    }
+
+    triangle
+}
+
+fn mesh_builder(
+    positions: &[Triple],
+    faces: &[(usize, usize, usize)],
+    normals: Option<&[Triple]>,
+    uvs: Option<&[Pair]>,
+    material: Arc<dyn Material>,
+) -> MeshBuilder {
+    let mut builder = MeshBuilder::new(
+        positions.iter().map(|&position| position.into()).collect(),
+        faces.iter().map(|&face| face.into()).collect(),
+        material,
+    );
+    if let Some(normals) = normals {
+        builder = builder.with_normals(normals.iter().map(|&normal| normal.into()).collect());
+    }
+    if let Some(uvs) = uvs {
+        builder = builder.with_uvs(uvs.to_vec());
+    }
+
+    builder
+}
+
+impl ObjectDescription {
+    fn material_references<'a>(&'a self, references: &mut Vec<&'a str>) {
+        match self {
+            Self::Sphere { material, .. }
+            | Self::Quad { material, .. }
+            | Self::Box { material, .. }
+            | Self::Triangle { material, .. }
+            | Self::Mesh { material, .. }
+            | Self::Obj {
+                material: Some(material),
+                ..
+            } => {
+                if let MaterialDescription::Named(name) = material {
+                    references.push(name);
+                }
+            }
+            Self::Obj { material: None, .. } => {}
+            Self::Translation { object, .. } | Self::RotationY { object, .. } => {
+                object.material_references(references);
+            }
+            Self::Group(objects) => {
+                for object in objects {
+                    object.material_references(references);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use crate::color::space::WorkingSpace;
+    use crate::color::Color;
+    use crate::hit::mesh::MeshError;
+    use crate::image::Image;
+
+    use super::{SceneError, SceneFile};
+
+    const SCENE: &str = r#"(
+    camera: (
+        focus_distance: 1.0,
+        defocus_angle: 0.0,
+        fov: 90.0,
+        samples: 4,
+        roulette_depth: 5,
+    ),
+    materials: {
+        "white": Lambertian(texture: Solid((1.0, 1.0, 1.0))),
+    },
+    objects: [
+        Sphere(center: (0.0, 0.0, -1.0), radius: 0.5, material: Named("white")),
+        Translation(
+            offset: (1.0, 0.0, 0.0),
+            object: Box(a: (0.0, 0.0, 0.0), b: (1.0, 1.0, 1.0), material: Named("missing")),
+        ),
+    ],
+)"#;
+
+    #[test]
+    fn unknown_material() {
+        let error = SceneFile::parse(SCENE.to_string(), PathBuf::new());
+
+        assert!(matches!(
+            error,
+            Err(SceneError::UnknownMaterial { name, line: Some(16) }) if name == "missing"
+        ));
+    }
+
+    #[test]
+    fn syntax_error() {
+        let error = SceneFile::parse(SCENE.replace("radius: 0.5", "radius 0.5"), PathBuf::new());
+
+        assert!(matches!(error, Err(SceneError::Parse { line: 13, .. })));
+    }
+
+    #[test]
+    fn camera_settings() {
+        let scene = SceneFile::parse(SCENE.replace("missing", "white"), PathBuf::new())
+            .expect("could not parse scene");
+
+        let (camera, _) = scene
+            .build(&Image::with_aspect_ratio(1, 1.0, Color::black()))
+            .expect("could not build scene");
+
+        assert_eq!(camera.samples, 4);
+        assert_eq!(camera.roulette_depth, 5);
+        assert_eq!(camera.seed, 0);
+        assert_eq!(camera.working_space, WorkingSpace::LinearSrgb);
+
+        let scene = SceneFile::parse(
+            SCENE
+                .replace("missing", "white")
+                .replace("samples: 4,", "samples: 4, seed: 9,"),
+            PathBuf::new(),
+        )
+        .expect("could not parse scene");
+        assert_eq!(scene.seed(), 9);
+        assert_eq!(scene.with_seed(3).seed(), 3);
+
+        let scene = SceneFile::parse(
+            SCENE
+                .replace("missing", "white")
+                .replace("samples: 4,", "samples: 4, working_space: AcesCg,"),
+            PathBuf::new(),
+        )
+        .expect("could not parse scene");
+        let (camera, _) = scene
+            .build(&Image::with_aspect_ratio(1, 1.0, Color::black()))
+            .expect("could not build scene");
+        assert_eq!(camera.working_space, WorkingSpace::AcesCg);
+    }
+
+    #[test]
+    fn invalid_mesh() {
+        let scene = SceneFile::parse(
+            SCENE.replace(
+                "Box(a: (0.0, 0.0, 0.0), b: (1.0, 1.0, 1.0), material: Named(\"missing\"))",
+                "Mesh(positions: [(0.0, 0.0, 0.0)], faces: [(0, 1, 2)], material: Named(\"white\"))",
+            ),
+            PathBuf::new(),
+        )
+        .expect("could not parse scene");
+
+        assert!(matches!(
+            scene.build_objects(),
+            Err(SceneError::Mesh(MeshError::IndexOutOfBounds {
+                face: 0,
+                index: 1
+            }))
+        ));
+    }
+
+    #[test]
+    fn included_scenes_parse() {
+        for entry in fs::read_dir("scenes").expect("could not read scenes") {
+            let path = entry.expect("could not read scene").path();
+
+            if let Err(error) = SceneFile::load(&path) {
+                panic!("{}: {error}", path.display());
+            }
+        }
+    }
+}
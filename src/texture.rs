@@ -1,3 +1,5 @@
+//! Colors that vary over the surface of an object.
+
 use crate::color::Color;
 use crate::vec::Vec3;
 
@@ -6,6 +8,7 @@ pub mod image;
 pub mod noise;
 pub mod solid_color;
 
+/// A color that depends on the surface coordinates and the position of a point.
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, point: &Vec3) -> Color;
 }
@@ -1,8 +1,9 @@
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::color::space::{ColorSpace, WorkingSpace};
 use crate::color::Color;
-use crate::image::Image;
+use crate::image::{Image, ImageError};
 use crate::texture::Texture;
 use crate::vec::Vec3;
 
@@ -11,10 +12,22 @@ pub struct ImageTexture {
 }
 
 impl ImageTexture {
-    pub fn load<P: AsRef<Path>>(path: P) -> Self {
-        Self {
-            image: Arc::new(Image::load(path)),
-        }
+    /// Load the texture from an image file storing its values in `color_space`, converting them
+    /// into `working_space`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image can't be loaded.
+    pub fn load<P: AsRef<Path>>(
+        path: P,
+        color_space: ColorSpace,
+        working_space: WorkingSpace,
+    ) -> Result<Self, ImageError> {
+        let image = Image::load(path)?.map(|color| color_space.decode(color, working_space));
+
+        Ok(Self {
+            image: Arc::new(image),
+        })
     }
 }
 
@@ -9,10 +9,11 @@ pub struct Perlin {
 }
 
 impl Perlin {
-    pub fn new(scale: f64) -> Self {
+    /// Noise scaled by `scale`, with the pattern chosen by `seed`.
+    pub fn new(scale: f64, seed: u64) -> Self {
         Self {
             scale,
-            generator: PerlinNoise::new(),
+            generator: PerlinNoise::new(seed),
         }
     }
 }
@@ -30,11 +31,13 @@ pub struct TurbulentPerlin {
 }
 
 impl TurbulentPerlin {
-    pub fn new(scale: f64, turbulence: u32) -> Self {
+    /// Turbulence of `turbulence` octaves of noise scaled by `scale`, with the pattern chosen by
+    /// `seed`.
+    pub fn new(scale: f64, turbulence: u32, seed: u64) -> Self {
         Self {
             scale,
             turbulence,
-            generator: PerlinNoise::new(),
+            generator: PerlinNoise::new(seed),
         }
     }
 }
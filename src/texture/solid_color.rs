@@ -4,7 +4,6 @@ use crate::vec::Vec3;
 
 pub struct SolidColor(pub Color);
 
-#[allow(dead_code)]
 impl SolidColor {
     pub const fn new(r: f64, g: f64, b: f64) -> Self {
         Self(Color::new(r, g, b))
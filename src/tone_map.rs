@@ -0,0 +1,287 @@
+//! Mapping the unbounded radiance of a render to the range a display can show.
+
+use crate::color::space::transform;
+use crate::color::Color;
+
+/// The smallest and largest exposure `AgX` maps, in stops relative to middle gray.
+const AGX_MIN_EV: f64 = -12.473_931;
+const AGX_MAX_EV: f64 = 4.026_069;
+/// Rotates and desaturates linear sRGB towards the primaries `AgX` works in.
+const AGX_INSET: [[f64; 3]; 3] = [
+    [
+        0.842_479_062_253_094,
+        0.078_433_599_999_999_2,
+        0.079_223_745_147_764_3,
+    ],
+    [
+        0.042_328_242_261_012_3,
+        0.878_468_636_469_772,
+        0.079_166_127_460_543_4,
+    ],
+    [0.042_375_654_905_705_1, 0.078_433_6, 0.879_142_973_793_104],
+];
+/// Undoes [`AGX_INSET`] after the curve.
+const AGX_OUTSET: [[f64; 3]; 3] = [
+    [
+        1.196_879_005_120_17,
+        -0.098_020_881_140_136_8,
+        -0.099_029_744_079_720_5,
+    ],
+    [
+        -0.052_896_851_757_456_2,
+        1.151_903_129_904_17,
+        -0.098_961_176_844_843_3,
+    ],
+    [
+        -0.052_971_635_514_443_8,
+        -0.098_043_450_117_124_1,
+        1.151_073_672_641_16,
+    ],
+];
+/// The display gamma the `AgX` curve is fitted for.
+const AGX_GAMMA: f64 = 2.2;
+
+/// Linear sRGB to the ACES working space, including the exposure the fit expects.
+const ACES_INPUT: [[f64; 3]; 3] = [
+    [0.597_19, 0.354_58, 0.048_23],
+    [0.076_00, 0.908_34, 0.015_66],
+    [0.028_40, 0.133_83, 0.837_77],
+];
+/// The ACES output space back to linear sRGB.
+const ACES_OUTPUT: [[f64; 3]; 3] = [
+    [1.604_75, -0.531_08, -0.073_67],
+    [-0.102_08, 1.108_13, -0.006_05],
+    [-0.003_27, -0.072_76, 1.076_02],
+];
+
+/// The exposure Hable's curve is usually applied with.
+const HABLE_EXPOSURE_BIAS: f64 = 2.0;
+/// The input Hable's curve maps to white.
+const HABLE_WHITE: f64 = 11.2;
+
+/// How colors brighter than white are brought into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapOperator {
+    /// Cut off every channel at 1. Keeps everything below white exactly as it is.
+    Clamp,
+    /// Reinhard's operator on the luminance, `L / (1 + L)`, which compresses every brightness
+    /// but never reaches white.
+    Reinhard,
+    /// Reinhard's operator extended to map the luminance `white` to white.
+    ExtendedReinhard { white: f64 },
+    /// Stephen Hill's fit of the ACES reference and output transforms, a filmic curve with a
+    /// slight contrast boost.
+    Aces,
+    /// Troy Sobotka's `AgX`, fitted by Benjamin Wrensch, which desaturates bright colors towards
+    /// white the way film does instead of skewing their hue.
+    AgX,
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+}
+
+/// Maps linear scene radiance to linear display values between 0 and 1.
+///
+/// The result still has to be encoded for the display, e.g. with
+/// [`Color::encode_srgb`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapper {
+    operator: ToneMapOperator,
+    exposure: f64,
+}
+
+impl Default for ToneMapper {
+    fn default() -> Self {
+        Self::new(ToneMapOperator::Clamp)
+    }
+}
+
+impl ToneMapper {
+    pub const fn new(operator: ToneMapOperator) -> Self {
+        Self {
+            operator,
+            exposure: 0.0,
+        }
+    }
+
+    /// Scale the radiance by `2^exposure` before mapping it, so every step of 1 doubles the
+    /// brightness.
+    pub const fn with_exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn apply(&self, color: Color) -> Color {
+        let color = color * self.exposure.exp2();
+
+        match self.operator {
+            ToneMapOperator::Clamp => color,
+            ToneMapOperator::Reinhard => {
+                scale_luminance(color, |luminance| luminance / (1.0 + luminance))
+            }
+            ToneMapOperator::ExtendedReinhard { white } => scale_luminance(color, |luminance| {
+                luminance * (1.0 + luminance / (white * white)) / (1.0 + luminance)
+            }),
+            ToneMapOperator::Aces => {
+                let color = transform(&ACES_INPUT, color);
+                transform(&ACES_OUTPUT, per_channel(color, aces_curve))
+            }
+            ToneMapOperator::AgX => {
+                let color = per_channel(transform(&AGX_INSET, color), |value| {
+                    let value = value.max(f64::MIN_POSITIVE).log2();
+                    agx_curve(
+                        (value.clamp(AGX_MIN_EV, AGX_MAX_EV) - AGX_MIN_EV)
+                            / (AGX_MAX_EV - AGX_MIN_EV),
+                    )
+                });
+                per_channel(transform(&AGX_OUTSET, color), |value| {
+                    value.max(0.0).powf(AGX_GAMMA)
+                })
+            }
+            ToneMapOperator::Hable => per_channel(color, |value| {
+                hable_curve(HABLE_EXPOSURE_BIAS * value) / hable_curve(HABLE_WHITE)
+            }),
+        }
+        .clamped()
+    }
+}
+
+/// Scale `color` so its luminance becomes `map` of its luminance, keeping its hue.
+fn scale_luminance(color: Color, map: impl Fn(f64) -> f64) -> Color {
+    let luminance = color.luminance();
+
+    if luminance > 0.0 {
+        color * (map(luminance) / luminance)
+    } else {
+        Color::black()
+    }
+}
+
+fn per_channel(color: Color, map: impl Fn(f64) -> f64) -> Color {
+    Color::new(map(color.r()), map(color.g()), map(color.b()))
+}
+
+fn aces_curve(value: f64) -> f64 {
+    let numerator = value.mul_add(value + 0.024_578_6, -0.000_090_537);
+    let denominator = value.mul_add(0.983_729f64.mul_add(value, 0.432_951), 0.238_081);
+
+    numerator / denominator
+}
+
+/// The polynomial fit of the default `AgX` contrast curve, from log-encoded values between 0 and 1
+/// to display encoded ones.
+fn agx_curve(x: f64) -> f64 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+
+    15.5f64.mul_add(
+        x4 * x2,
+        (-40.14f64).mul_add(
+            x4 * x,
+            31.96f64.mul_add(
+                x4,
+                (-6.868f64).mul_add(
+                    x2 * x,
+                    0.4298f64.mul_add(x2, 0.1191f64.mul_add(x, -0.002_32)),
+                ),
+            ),
+        ),
+    )
+}
+
+fn hable_curve(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+
+    x.mul_add(A.mul_add(x, C * B), D * E) / x.mul_add(A.mul_add(x, B), D * F) - E / F
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::color::Color;
+
+    use super::{ToneMapOperator, ToneMapper};
+
+    const OPERATORS: [ToneMapOperator; 6] = [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ExtendedReinhard { white: 4.0 },
+        ToneMapOperator::Aces,
+        ToneMapOperator::AgX,
+        ToneMapOperator::Hable,
+    ];
+
+    fn gray(value: f64) -> Color {
+        Color::new(value, value, value)
+    }
+
+    #[test]
+    fn exposure() {
+        let mapper = ToneMapper::default().with_exposure(2.0);
+
+        assert_abs_diff_eq!(mapper.apply(gray(0.1)), gray(0.4), epsilon = 1e-12);
+        assert_abs_diff_eq!(mapper.apply(gray(0.5)), Color::white());
+    }
+
+    #[test]
+    fn reinhard() {
+        let reinhard = ToneMapper::new(ToneMapOperator::Reinhard);
+        let extended = ToneMapper::new(ToneMapOperator::ExtendedReinhard { white: 4.0 });
+
+        assert_abs_diff_eq!(reinhard.apply(gray(1.0)), gray(0.5), epsilon = 1e-12);
+        assert_abs_diff_eq!(extended.apply(gray(4.0)), Color::white(), epsilon = 1e-12);
+        // keeps the hue
+        let color = reinhard.apply(Color::new(2.0, 1.0, 0.0));
+        assert_abs_diff_eq!(color.r(), 2.0 * color.g(), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn displayable_and_monotonic() {
+        for operator in OPERATORS {
+            let mapper = ToneMapper::new(operator);
+            let values = [0.0, 0.01, 0.18, 0.5, 1.0, 2.0, 10.0, 1000.0]
+                .map(|value| mapper.apply(gray(value)).g());
+
+            assert!(
+                values[0] < 0.01,
+                "{operator:?} lifts black to {}",
+                values[0]
+            );
+            assert!(
+                values.windows(2).all(|pair| pair[0] <= pair[1]),
+                "{operator:?} is not monotonic: {values:?}"
+            );
+            assert!(
+                values.iter().all(|value| (0.0..=1.0).contains(value)),
+                "{operator:?} leaves the displayable range: {values:?}"
+            );
+            // middle gray stays recognizably dark
+            assert!(
+                (0.05..0.4).contains(&values[2]),
+                "{operator:?} maps middle gray to {}",
+                values[2]
+            );
+        }
+    }
+
+    #[test]
+    fn filmic_operators_compress_highlights() {
+        for operator in [
+            ToneMapOperator::Aces,
+            ToneMapOperator::AgX,
+            ToneMapOperator::Hable,
+        ] {
+            let mapper = ToneMapper::new(operator);
+
+            assert!(
+                mapper.apply(gray(2.0)).g() < mapper.apply(gray(8.0)).g(),
+                "{operator:?} blows out highlights"
+            );
+        }
+    }
+}
@@ -1,11 +1,16 @@
+//! A window showing the progress of a render.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::{sync::mpsc::Receiver, time::Duration};
 
 use eframe::{App, Frame, NativeOptions};
 use egui::{
-    load::SizedTexture, CentralPanel, ColorImage, Context, TextureOptions, ViewportBuilder,
-    ViewportCommand,
+    load::SizedTexture, CentralPanel, ColorImage, Context, TextureOptions, TopBottomPanel,
+    ViewportBuilder, ViewportCommand,
 };
 
+use crate::tone_map::{ToneMapOperator, ToneMapper};
 use crate::{color::Color, image::Image};
 
 #[derive(Debug)]
@@ -13,9 +18,10 @@ pub struct Preview {
     shutdown_rx: Receiver<()>,
     samples_rx: Receiver<((u32, u32), Color)>,
     render_target: Image,
+    tone_mapper: ToneMapper,
+    stop: Option<Arc<AtomicBool>>,
 }
 
-#[allow(dead_code)]
 impl Preview {
     pub const fn new(
         shutdown_rx: Receiver<()>,
@@ -26,16 +32,34 @@ impl Preview {
             shutdown_rx,
             samples_rx,
             render_target,
+            tone_mapper: ToneMapper::new(ToneMapOperator::Clamp),
+            stop: None,
         }
     }
 
-    pub fn run(self) {
+    /// Map the render to the display with `tone_mapper`, instead of clamping it.
+    pub const fn with_tone_mapper(mut self, tone_mapper: ToneMapper) -> Self {
+        self.tone_mapper = tone_mapper;
+        self
+    }
+
+    /// Show a button that sets `stop` to end the render early.
+    pub fn with_stop(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Open the preview window and block until it is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the window can't be opened, e.g. because there is no display.
+    pub fn run(self) -> Result<(), eframe::Error> {
         let options = NativeOptions {
             viewport: ViewportBuilder::default().with_title("raytracer"),
             ..Default::default()
         };
         eframe::run_native("raytracer", options, Box::new(|_| Ok(Box::new(self))))
-            .expect("Could not start preview");
     }
 }
 
@@ -48,9 +72,12 @@ impl App for Preview {
         while let Ok(((x, y), color)) = self.samples_rx.try_recv() {
             self.render_target.set_pixel(x, y, color);
         }
+        let display = self
+            .render_target
+            .map(|color| self.tone_mapper.apply(color).encode_srgb());
         let texture_handle = ctx.load_texture(
             "preview".to_string(),
-            ColorImage::from(&self.render_target),
+            ColorImage::from(&display),
             TextureOptions::default(),
         );
         #[allow(clippy::cast_precision_loss)]
@@ -62,6 +89,17 @@ impl App for Preview {
             ),
         );
 
+        if let Some(stop) = &self.stop {
+            TopBottomPanel::bottom("controls").show(ctx, |ui| {
+                let stopped = stop.load(Ordering::Relaxed);
+                if ui
+                    .add_enabled(!stopped, egui::Button::new("Stop"))
+                    .clicked()
+                {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
         CentralPanel::default().show(ctx, |ui| {
             ui.centered_and_justified(|ui| ui.image(texture));
         });
@@ -1,3 +1,6 @@
+//! Three-dimensional vectors.
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
 use std::fmt::{Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Range, Sub, SubAssign};
 use std::vec::IntoIter;
@@ -7,7 +10,6 @@ use approx::AbsDiffEq;
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
 pub struct Vec3(pub f64, pub f64, pub f64);
 
-#[allow(dead_code)]
 impl Vec3 {
     pub const fn zero() -> Self {
         Self(0.0, 0.0, 0.0)
@@ -44,13 +46,7 @@ impl Vec3 {
     }
 
     pub fn random_unit_vector() -> Self {
-        loop {
-            let p = Self::random_in_range(-1.0..1.0);
-
-            if p.len_sq() < 1.0 {
-                return p.normalized();
-            }
-        }
+        Self::on_unit_sphere((rand::random(), rand::random()))
     }
 
     pub fn random_on_hemisphere(normal: &Self) -> Self {
@@ -63,17 +59,41 @@ impl Vec3 {
         }
     }
 
-    pub fn random_in_unit_disk() -> Self {
-        loop {
-            let mut p = Self::random_in_range(-1.0..1.0);
-            p.2 = 0.0;
+    /// Map two numbers in `[0, 1)` to a point on the unit sphere, uniformly distributed if the
+    /// numbers are.
+    pub fn on_unit_sphere(u: (f64, f64)) -> Self {
+        let z = 2.0f64.mul_add(-u.0, 1.0);
+        let r = z.mul_add(-z, 1.0).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+
+        Self(r * phi.cos(), r * phi.sin(), z)
+    }
 
-            if p.len_sq() < 1.0 {
-                return p;
-            }
+    /// Map two numbers in `[0, 1)` to a point in the unit disk in the xy plane, uniformly
+    /// distributed if the numbers are.
+    ///
+    /// Uses Shirley and Chiu's concentric mapping, which keeps evenly spread numbers evenly
+    /// spread.
+    pub fn in_unit_disk(u: (f64, f64)) -> Self {
+        let (x, y) = (2.0f64.mul_add(u.0, -1.0), 2.0f64.mul_add(u.1, -1.0));
+        if x == 0.0 && y == 0.0 {
+            return Self::zero();
         }
+
+        let (r, theta) = if x.abs() > y.abs() {
+            (x, FRAC_PI_4 * (y / x))
+        } else {
+            (y, FRAC_PI_4.mul_add(-(x / y), FRAC_PI_2))
+        };
+
+        Self(r * theta.cos(), r * theta.sin(), 0.0)
     }
 
+    /// The component along axis `n` (0 = x, 1 = y, 2 = z).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not a valid axis.
     pub fn axis(&self, n: u32) -> f64 {
         match n {
             0 => self.0,
@@ -83,6 +103,11 @@ impl Vec3 {
         }
     }
 
+    /// Set the component along axis `n` (0 = x, 1 = y, 2 = z).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not a valid axis.
     pub fn set_axis(&mut self, n: u32, value: f64) {
         match n {
             0 => self.0 = value,
@@ -145,11 +170,11 @@ impl Vec3 {
         )
     }
 
-    pub fn floored(&self) -> Self {
+    pub const fn floored(&self) -> Self {
         Self(self.0.floor(), self.1.floor(), self.2.floor())
     }
 
-    pub fn floor(&mut self) {
+    pub const fn floor(&mut self) {
         self.0 = self.0.floor();
         self.1 = self.1.floor();
         self.2 = self.2.floor();
@@ -286,6 +311,12 @@ impl AbsDiffEq for Vec3 {
     }
 }
 
+impl From<(f64, f64, f64)> for Vec3 {
+    fn from(value: (f64, f64, f64)) -> Self {
+        Self(value.0, value.1, value.2)
+    }
+}
+
 impl IntoIterator for Vec3 {
     type Item = f64;
     type IntoIter = IntoIter<f64>;
@@ -378,4 +409,19 @@ mod tests {
 
         assert_abs_diff_eq!(v1.cross(&v2), Vec3(-3.0, 6.0, -3.0));
     }
+
+    #[test]
+    fn sample_mappings() {
+        for (i, j) in (0..10).flat_map(|i| (0..10).map(move |j| (i, j))) {
+            let u = (f64::from(i) / 10.0, f64::from(j) / 10.0);
+
+            assert_abs_diff_eq!(Vec3::on_unit_sphere(u).len(), 1.0, epsilon = 1e-12);
+            let disk = Vec3::in_unit_disk(u);
+            assert!(disk.len() <= 1.0 + 1e-12 && disk.2 == 0.0);
+        }
+
+        assert_abs_diff_eq!(Vec3::on_unit_sphere((0.0, 0.3)), Vec3(0.0, 0.0, 1.0));
+        assert_abs_diff_eq!(Vec3::in_unit_disk((0.5, 0.5)), Vec3::zero());
+        assert_abs_diff_eq!(Vec3::in_unit_disk((1.0, 0.5)), Vec3(1.0, 0.0, 0.0));
+    }
 }
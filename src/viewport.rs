@@ -50,19 +50,12 @@ impl Viewport {
         }
     }
 
-    pub fn pixel_sample(
-        &self,
-        x: u32,
-        y: u32,
-        sample_x: u32,
-        sample_y: u32,
-        subpixel_scale: f64,
-    ) -> Vec3 {
+    /// The point at `offset` within the pixel at `x`, `y`, with the offset ranging from 0 to 1
+    /// across the pixel.
+    pub fn pixel_sample(&self, x: u32, y: u32, offset: (f64, f64)) -> Vec3 {
         self.origin
-            + self.pixel_size.0
-                * subpixel_scale.mul_add(f64::from(sample_x) + rand::random::<f64>(), f64::from(x))
-            + self.pixel_size.1
-                * subpixel_scale.mul_add(f64::from(sample_y) + rand::random::<f64>(), f64::from(y))
+            + self.pixel_size.0 * (f64::from(x) + offset.0)
+            + self.pixel_size.1 * (f64::from(y) + offset.1)
     }
 
     pub const fn pixel_size(&self) -> (Vec3, Vec3) {